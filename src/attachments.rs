@@ -0,0 +1,211 @@
+use crate::serialise::serialise_data;
+use crate::storage::{Storage, StorageError};
+use chrono::Utc;
+use image::{ImageFormat, ImageOutputFormat};
+use rusqlite::{params, Connection, Error, Row};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+// longest side of a thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 256;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub project_id: u8,
+    pub user_id: u8,
+    pub uploader_email: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u64,
+    pub storage_key: String,
+    pub thumbnail_key: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug)]
+pub struct AttachmentInput<'a> {
+    pub file_name: &'a str,
+    pub content_type: &'a str,
+    pub size: u64,
+    pub storage_key: &'a str,
+    pub thumbnail_key: Option<&'a str>,
+}
+
+const ATTACHMENT_COLUMNS: &str = "attachment.id, attachment.project_id, attachment.user_id,
+    user.email, attachment.file_name, attachment.content_type, attachment.size,
+    attachment.storage_key, attachment.thumbnail_key, attachment.created_at";
+
+fn attachment_from_row(row: &Row) -> Result<Attachment, Error> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        user_id: row.get(2)?,
+        uploader_email: row.get(3)?,
+        file_name: row.get(4)?,
+        content_type: row.get(5)?,
+        size: row.get(6)?,
+        storage_key: row.get(7)?,
+        thumbnail_key: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+pub fn query_attachments_for_project(project_id: u8) -> Result<Vec<Attachment>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM attachment
+            JOIN user ON attachment.user_id = user.id
+            WHERE attachment.project_id = ?1
+            ORDER BY attachment.id DESC",
+        ATTACHMENT_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![project_id], attachment_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+pub fn query_attachment_by_id(project_id: u8, attachment_id: i64) -> Result<Attachment, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM attachment
+                JOIN user ON attachment.user_id = user.id
+                WHERE attachment.project_id = ?1 AND attachment.id = ?2",
+            ATTACHMENT_COLUMNS
+        ),
+        params![project_id, attachment_id],
+        attachment_from_row,
+    )
+}
+
+pub fn add_attachment(
+    project_id: u8,
+    user_id: u8,
+    attachment: &AttachmentInput,
+) -> Result<i64, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO attachment
+            (project_id, user_id, file_name, content_type, size, storage_key, thumbnail_key)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            project_id,
+            user_id,
+            attachment.file_name,
+            attachment.content_type,
+            attachment.size,
+            attachment.storage_key,
+            attachment.thumbnail_key
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn delete_attachment(project_id: u8, attachment_id: i64) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "DELETE FROM attachment WHERE project_id = ?1 AND id = ?2",
+        params![project_id, attachment_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// every stored file of a project, so they can be removed along with it
+pub fn query_storage_keys_for_project(project_id: u8) -> Result<Vec<String>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT storage_key FROM attachment WHERE project_id = ?1
+            UNION ALL
+            SELECT thumbnail_key FROM attachment
+                WHERE project_id = ?1 AND thumbnail_key IS NOT NULL",
+    )?;
+    let items_iter = statement.query_map(params![project_id], |row| row.get(0))?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// what the file really is, going by its contents rather than what the browser claimed
+pub fn sniff_content_type(bytes: &[u8], declared: &str) -> &'static str {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Png) => return "image/png",
+        Ok(ImageFormat::Jpeg) => return "image/jpeg",
+        Ok(ImageFormat::Gif) => return "image/gif",
+        _ => {}
+    }
+    if bytes.starts_with(b"%PDF-") {
+        "application/pdf"
+    } else if std::str::from_utf8(bytes).is_ok() {
+        if declared == "text/markdown" {
+            "text/markdown"
+        } else {
+            "text/plain"
+        }
+    } else {
+        "application/octet-stream"
+    }
+}
+
+// keeps letters, digits, dots, dashes and underscores so names are safe in paths and headers
+pub fn sanitise_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches('.');
+    if name.is_empty() {
+        "file".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+pub fn storage_key(project_id: u8, file_name: &str) -> String {
+    format!(
+        "{}/{}-{}",
+        project_id,
+        Utc::now().format("%Y%m%d%H%M%S%f"),
+        file_name
+    )
+}
+
+// a PNG no larger than THUMBNAIL_SIZE on either side; `None` for anything that isn't an image
+pub fn make_thumbnail(bytes: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(bytes).ok()?;
+    let mut thumbnail = Vec::new();
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut thumbnail), ImageOutputFormat::Png)
+        .ok()?;
+    Some(thumbnail)
+}
+
+// stores the file and, for images, its thumbnail; returns the thumbnail's key
+pub fn store_upload(
+    storage: &dyn Storage,
+    key: &str,
+    bytes: &[u8],
+    is_image: bool,
+) -> Result<Option<String>, StorageError> {
+    storage.put(key, bytes)?;
+    if !is_image {
+        return Ok(None);
+    }
+    match make_thumbnail(bytes) {
+        Some(thumbnail) => {
+            let thumbnail_key = format!("thumbnails/{}.png", key);
+            storage.put(&thumbnail_key, &thumbnail)?;
+            Ok(Some(thumbnail_key))
+        }
+        None => Ok(None),
+    }
+}
@@ -1,4 +1,8 @@
-use std::{thread, time::Duration};
+use crate::config::AppConfig;
+use crate::deadlines::check_deadlines;
+use crate::mail::{mailer_from_config, Mailer};
+use rocket::tokio::task::spawn_blocking;
+use std::{sync::Arc, thread, time::Duration};
 
 pub fn print_hello() {
     loop {
@@ -6,3 +10,27 @@ pub fn print_hello() {
         thread::sleep(Duration::from_secs(5));
     }
 }
+
+pub async fn remind_deadlines(config: AppConfig) {
+    let mailer: Arc<dyn Mailer> = Arc::from(mailer_from_config(&config));
+    // tokio panics on a zero period
+    let secs = if config.deadline_check_interval_secs == 0 {
+        println!("deadline_check_interval_secs must be at least 1, checking every second");
+        1
+    } else {
+        config.deadline_check_interval_secs
+    };
+    let mut interval = rocket::tokio::time::interval(Duration::from_secs(secs));
+    loop {
+        interval.tick().await;
+        // SQLite and the mail backend block, so keep them off the async workers
+        let mailer = mailer.clone();
+        let days = config.deadline_reminder_days;
+        match spawn_blocking(move || check_deadlines(days, mailer.as_ref())).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(count)) => println!("Deadline reminders sent for {} projects 📅", count),
+            Ok(Err(e)) => println!("Deadline check failed: {}", e),
+            Err(e) => println!("Deadline check panicked: {}", e),
+        }
+    }
+}
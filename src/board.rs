@@ -0,0 +1,224 @@
+use crate::db_queries::{
+    query_all_projects_for_user, update_project_status, Project, ProjectStatus, StatusError, User,
+};
+use crate::serialise::serialise_data;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub enum BoardError {
+    NotFound,
+    NotAllowed,
+    WipLimit(ProjectStatus, u32),
+    DatabaseError(rusqlite::Error),
+}
+
+impl std::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BoardError::NotFound => write!(f, "Project not found"),
+            BoardError::NotAllowed => write!(f, "Only owners and editors can change the status"),
+            BoardError::WipLimit(status, limit) => write!(
+                f,
+                "{} is limited to {} projects",
+                status.as_str().replace('_', " "),
+                limit
+            ),
+            BoardError::DatabaseError(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+impl From<rusqlite::Error> for BoardError {
+    fn from(e: rusqlite::Error) -> Self {
+        BoardError::DatabaseError(e)
+    }
+}
+
+// a workspace's setting for one status column
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ColumnSettings {
+    pub status: ProjectStatus,
+    pub wip_limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BoardColumn {
+    pub status: ProjectStatus,
+    pub wip_limit: Option<u32>,
+    // every project in the workspace with this status, including ones the user can't see
+    pub count: usize,
+    pub over_limit: bool,
+    pub cards: Vec<Project>,
+}
+
+// every status in the workspace's order; statuses it hasn't placed keep their usual spot
+pub fn query_column_settings(team_id: i64) -> Result<Vec<ColumnSettings>, rusqlite::Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement =
+        conn.prepare("SELECT status, position, wip_limit FROM board_column WHERE team_id = ?1")?;
+    let items_iter = statement.query_map(params![team_id], |row| {
+        Ok((
+            row.get::<_, ProjectStatus>(0)?,
+            (row.get::<_, i64>(1)?, row.get(2)?),
+        ))
+    })?;
+    let saved: HashMap<&str, (i64, Option<u32>)> = serialise_data(items_iter)
+        .into_iter()
+        .map(|(status, settings)| (status.as_str(), settings))
+        .collect();
+
+    let mut columns: Vec<(i64, ColumnSettings)> = ProjectStatus::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, status)| {
+            let (position, wip_limit) = saved
+                .get(status.as_str())
+                .copied()
+                .unwrap_or((index as i64, None));
+            (
+                position,
+                ColumnSettings {
+                    status: *status,
+                    wip_limit,
+                },
+            )
+        })
+        .collect();
+    columns.sort_by_key(|(position, _)| *position);
+    Ok(columns.into_iter().map(|(_, column)| column).collect())
+}
+
+// `columns` in the order they should appear
+pub fn save_column_settings(
+    team_id: i64,
+    columns: &[ColumnSettings],
+) -> Result<(), rusqlite::Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    for (position, column) in columns.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO board_column (team_id, status, position, wip_limit)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (team_id, status)
+                    DO UPDATE SET position = excluded.position, wip_limit = excluded.wip_limit",
+            params![team_id, column.status, position, column.wip_limit],
+        )?;
+    }
+    tx.commit()
+}
+
+fn status_counts(
+    conn: &Connection,
+    team_id: i64,
+) -> Result<HashMap<String, usize>, rusqlite::Error> {
+    let mut statement =
+        conn.prepare("SELECT status, COUNT(*) FROM project WHERE team_id = ?1 GROUP BY status")?;
+    let items_iter = statement.query_map(params![team_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    Ok(serialise_data(items_iter).into_iter().collect())
+}
+
+fn card_positions(conn: &Connection, user_id: u8) -> Result<HashMap<u8, i64>, rusqlite::Error> {
+    let mut statement =
+        conn.prepare("SELECT project_id, position FROM board_card WHERE user_id = ?1")?;
+    let items_iter = statement.query_map(params![user_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    Ok(serialise_data(items_iter).into_iter().collect())
+}
+
+// the user's projects in the workspace, sorted into status columns in their own order;
+// cards they never moved go last, oldest first
+pub fn query_board(user_id: u8, team_id: i64) -> Result<Vec<BoardColumn>, rusqlite::Error> {
+    let projects = query_all_projects_for_user(user_id, team_id)?;
+    let conn = Connection::open("db.sqlite")?;
+    let positions = card_positions(&conn, user_id)?;
+    let counts = status_counts(&conn, team_id)?;
+
+    let mut columns: Vec<BoardColumn> = query_column_settings(team_id)?
+        .into_iter()
+        .map(|column| BoardColumn {
+            status: column.status,
+            wip_limit: column.wip_limit,
+            count: counts
+                .get(column.status.as_str())
+                .copied()
+                .unwrap_or_default(),
+            over_limit: false,
+            cards: Vec::new(),
+        })
+        .collect();
+    for project in projects {
+        if let Some(column) = columns
+            .iter_mut()
+            .find(|column| column.status == project.status)
+        {
+            column.cards.push(project);
+        }
+    }
+    for column in &mut columns {
+        column.cards.sort_by_key(|card| {
+            let id = card.id_proj.unwrap_or_default();
+            (positions.get(&id).copied().unwrap_or(i64::MAX), id)
+        });
+        column.over_limit = column
+            .wip_limit
+            .is_some_and(|limit| column.count > limit as usize);
+    }
+    Ok(columns)
+}
+
+// drops the card into `status` at `position`; moving to another column changes the project's
+// status, which needs edit rights and room under the column's WIP limit across the workspace
+pub fn move_card(
+    user: &User,
+    team_id: i64,
+    project_id: u8,
+    status: ProjectStatus,
+    position: usize,
+) -> Result<(), BoardError> {
+    let columns = query_board(user.id, team_id)?;
+    let current = columns
+        .iter()
+        .find(|column| {
+            column
+                .cards
+                .iter()
+                .any(|card| card.id_proj == Some(project_id))
+        })
+        .ok_or(BoardError::NotFound)?;
+    let target = columns
+        .iter()
+        .find(|column| column.status == status)
+        .ok_or(BoardError::NotFound)?;
+
+    if current.status != status {
+        update_project_status(project_id, team_id, status, user).map_err(|e| match e {
+            StatusError::WipLimit(status, limit) => BoardError::WipLimit(status, limit),
+            StatusError::DatabaseError(_) => BoardError::NotAllowed,
+        })?;
+    }
+
+    let mut order: Vec<u8> = target
+        .cards
+        .iter()
+        .filter_map(|card| card.id_proj)
+        .filter(|id| *id != project_id)
+        .collect();
+    order.insert(position.min(order.len()), project_id);
+
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    for (position, id) in order.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO board_card (user_id, project_id, position) VALUES (?1, ?2, ?3)
+                ON CONFLICT (user_id, project_id) DO UPDATE SET position = excluded.position",
+            params![user.id, id, position],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use crate::db_queries::{Project, ProjectStatus};
+use crate::gantt::day;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+// something happening to a project on a given day
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalendarEvent {
+    pub project_id: u8,
+    pub name: String,
+    pub status: ProjectStatus,
+    // "start", "end" or "due"
+    pub kind: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalendarDay {
+    pub date: String,
+    pub day: u32,
+    pub in_month: bool,
+    pub today: bool,
+    pub events: Vec<CalendarEvent>,
+}
+
+// the weeks, Monday first, covering the month `first` starts
+pub fn month_grid(
+    first: NaiveDate,
+    projects: &[Project],
+    today: NaiveDate,
+) -> Vec<Vec<CalendarDay>> {
+    let mut events: Vec<(NaiveDate, CalendarEvent)> = Vec::new();
+    for project in projects {
+        let dates = [
+            ("start", &project.start_date),
+            ("end", &project.end_date),
+            ("due", &project.due_date),
+        ];
+        for (kind, date) in dates {
+            if let Some(date) = day(date) {
+                events.push((
+                    date,
+                    CalendarEvent {
+                        project_id: project.id_proj.unwrap_or_default(),
+                        name: project.name.clone(),
+                        status: project.status,
+                        kind: kind.to_string(),
+                    },
+                ));
+            }
+        }
+    }
+    events.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+
+    let mut date = first - Duration::days(first.weekday().num_days_from_monday().into());
+    let mut weeks = Vec::new();
+    while weeks.is_empty() || date.month() == first.month() {
+        let week = (0..7)
+            .map(|offset| {
+                let current = date + Duration::days(offset);
+                CalendarDay {
+                    date: current.format("%Y-%m-%d").to_string(),
+                    day: current.day(),
+                    in_month: current.month() == first.month(),
+                    today: current == today,
+                    events: events
+                        .iter()
+                        .filter(|(event_date, _)| *event_date == current)
+                        .map(|(_, event)| event.clone())
+                        .collect(),
+                }
+            })
+            .collect();
+        weeks.push(week);
+        date += Duration::days(7);
+    }
+    weeks
+}
+
+pub fn query_calendar_token(user_id: u8) -> Result<Option<String>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT token FROM calendar_feed WHERE user_id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+// a new secret for the feed URL; the old one stops working straight away
+pub fn regenerate_calendar_token(user_id: u8) -> Result<String, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO calendar_feed (user_id, token) VALUES (?1, lower(hex(randomblob(16))))
+            ON CONFLICT (user_id) DO UPDATE SET token = excluded.token,
+                created_at = CURRENT_TIMESTAMP",
+        params![user_id],
+    )?;
+    conn.query_row(
+        "SELECT token FROM calendar_feed WHERE user_id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )
+}
+
+// whose feed a token opens, if anyone's
+pub fn query_calendar_token_user(token: &str) -> Result<Option<u8>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT user_id FROM calendar_feed WHERE token = ?1",
+        params![token],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+// commas, semicolons, backslashes and line breaks are special in text values
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+// lines end in CRLF and are folded at 75 octets, without splitting a character
+fn push_line(ics: &mut String, line: &str) {
+    let mut length = 0;
+    for character in line.chars() {
+        if length + character.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            length = 1;
+        }
+        ics.push(character);
+        length += character.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+fn ics_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+// an all-day event over `start` to `end`, both included
+fn push_event(
+    ics: &mut String,
+    uid: &str,
+    stamp: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    summary: &str,
+    project: &Project,
+) {
+    push_line(ics, "BEGIN:VEVENT");
+    push_line(ics, &format!("UID:{}@rocket-db-forms", uid));
+    push_line(ics, &format!("DTSTAMP:{}", stamp));
+    push_line(ics, &format!("DTSTART;VALUE=DATE:{}", ics_date(start)));
+    // the end of an all-day event is the day after it
+    push_line(
+        ics,
+        &format!("DTEND;VALUE=DATE:{}", ics_date(end + Duration::days(1))),
+    );
+    push_line(ics, &format!("SUMMARY:{}", escape_text(summary)));
+    push_line(
+        ics,
+        &format!(
+            "DESCRIPTION:{}",
+            escape_text(
+                format!(
+                    "Status: {}, priority: {}\n\n{}",
+                    project.status.as_str().replace('_', " "),
+                    project.priority.as_str(),
+                    project.description
+                )
+                .trim_end()
+            )
+        ),
+    );
+    if project.status == ProjectStatus::Cancelled {
+        push_line(ics, "STATUS:CANCELLED");
+    }
+    push_line(ics, "TRANSP:TRANSPARENT");
+    push_line(ics, "END:VEVENT");
+}
+
+// an RFC 5545 calendar with each project running from its start to its end date, or just its
+// start day while it has no end, and its due date as a day of its own
+pub fn render_ics(projects: &[Project]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//rocket-db-forms//projects//EN");
+    push_line(&mut ics, "CALSCALE:GREGORIAN");
+    push_line(&mut ics, "METHOD:PUBLISH");
+    push_line(&mut ics, "X-WR-CALNAME:Projects");
+    for project in projects {
+        let id = project.id_proj.unwrap_or_default();
+        if let Some(start) = day(&project.start_date) {
+            let end = day(&project.end_date).unwrap_or(start).max(start);
+            push_event(
+                &mut ics,
+                &format!("project-{}", id),
+                &stamp,
+                start,
+                end,
+                &project.name,
+                project,
+            );
+        }
+        if let Some(due) = day(&project.due_date) {
+            push_event(
+                &mut ics,
+                &format!("project-{}-due", id),
+                &stamp,
+                due,
+                due,
+                &format!("{} due", project.name),
+                project,
+            );
+        }
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+// "2024-05" as the first of that month
+pub fn parse_month(month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folded(line: &str) -> String {
+        let mut ics = String::new();
+        push_line(&mut ics, line);
+        ics
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape_text(r"a,b;c\d"), r"a\,b\;c\\d");
+        assert_eq!(
+            escape_text("one\r\ntwo\nthree\rfour"),
+            r"one\ntwo\nthreefour"
+        );
+    }
+
+    #[test]
+    fn escapes_backslashes_before_adding_its_own() {
+        assert_eq!(escape_text(r"\n"), r"\\n");
+    }
+
+    #[test]
+    fn short_lines_are_left_alone() {
+        assert_eq!(folded("SUMMARY:Launch"), "SUMMARY:Launch\r\n");
+        let exact = "x".repeat(75);
+        assert_eq!(folded(&exact), format!("{}\r\n", exact));
+    }
+
+    #[test]
+    fn long_lines_fold_at_75_octets() {
+        let ics = folded(&"x".repeat(200));
+        let lines: Vec<&str> = ics.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        // continuation lines start with a space, which counts towards their 75
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines[1].len(), 75);
+        assert_eq!(lines[2].len(), 1 + 200 - 75 - 74);
+        let unfolded: String = lines.concat().replace(' ', "");
+        assert_eq!(unfolded, "x".repeat(200));
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        // 74 single bytes, then a 3-byte character that would straddle the limit
+        let line = format!("{}€€", "x".repeat(74));
+        let ics = folded(&line);
+        let lines: Vec<&str> = ics.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines, vec!["x".repeat(74), " €€".to_string()]);
+        assert!(lines.iter().all(|line| line.len() <= 75));
+    }
+}
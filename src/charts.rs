@@ -0,0 +1,113 @@
+use crate::gantt::escape_xml;
+use crate::stats::WeekActivity;
+use std::fmt::Write;
+
+const LABEL_WIDTH: i64 = 180;
+const BAR_AREA: i64 = 360;
+const ROW_HEIGHT: i64 = 24;
+const COLUMN_AREA: i64 = 160;
+const CREATED_COLOUR: &str = "#1e88e5";
+const COMPLETED_COLOUR: &str = "#43a047";
+
+// one labelled horizontal bar per entry, scaled to the largest value
+pub fn bar_chart(entries: &[(String, usize)], colour: &str, description: &str) -> String {
+    let largest = entries
+        .iter()
+        .map(|(_, value)| *value)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let width = LABEL_WIDTH + BAR_AREA + 40;
+    let height = ROW_HEIGHT * (entries.len() as i64).max(1);
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="12" role="img" aria-label="{label}">"##,
+        label = escape_xml(description),
+    );
+    for (row, (label, value)) in entries.iter().enumerate() {
+        let top = ROW_HEIGHT * row as i64;
+        let bar_width = BAR_AREA * *value as i64 / largest as i64;
+        let _ = write!(
+            svg,
+            r##"<text x="{text_x}" y="{text_y}" fill="currentColor" text-anchor="end">{label}</text><rect x="{LABEL_WIDTH}" y="{bar_y}" width="{bar_width}" height="{bar_height}" rx="2" fill="{colour}"><title>{label}: {value}</title></rect><text x="{value_x}" y="{text_y}" fill="currentColor">{value}</text>"##,
+            text_x = LABEL_WIDTH - 8,
+            text_y = top + ROW_HEIGHT / 2 + 4,
+            label = escape_xml(label),
+            bar_y = top + 4,
+            bar_height = ROW_HEIGHT - 8,
+            value_x = LABEL_WIDTH + bar_width + 6,
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// a pair of columns per week, projects created next to projects done
+pub fn weekly_chart(weeks: &[WeekActivity]) -> String {
+    let largest = weeks
+        .iter()
+        .map(|week| week.created.max(week.completed))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let slot = 36;
+    let bottom = 20 + COLUMN_AREA;
+    let width = 40 + slot * weeks.len() as i64;
+    let height = bottom + 40;
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="11" role="img" aria-label="projects created and done per week">"##
+    );
+    let _ = write!(
+        svg,
+        r##"<line x1="20" y1="{bottom}" x2="{width}" y2="{bottom}" stroke="#888" stroke-opacity="0.5"/><text x="4" y="14" fill="#888">{largest}</text>"##
+    );
+    for (index, week) in weeks.iter().enumerate() {
+        let left = 30 + slot * index as i64;
+        for (offset, (count, colour, what)) in [
+            (week.created, CREATED_COLOUR, "created"),
+            (week.completed, COMPLETED_COLOUR, "done"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let column_height = COLUMN_AREA * count as i64 / largest as i64;
+            let _ = write!(
+                svg,
+                r##"<rect x="{x}" y="{y}" width="12" height="{column_height}" fill="{colour}"><title>week of {week_label}: {count} {what}</title></rect>"##,
+                x = left + 13 * offset as i64,
+                y = bottom - column_height,
+                week_label = week.week,
+            );
+        }
+        // every other week is labelled, the labels wouldn't fit otherwise
+        if index % 2 == 0 {
+            let _ = write!(
+                svg,
+                r##"<text x="{left}" y="{label_y}" fill="#888">{label}</text>"##,
+                label_y = bottom + 16,
+                label = week.week.get(5..).unwrap_or_default(),
+            );
+        }
+    }
+    let _ = write!(
+        svg,
+        r##"<rect x="30" y="{legend_y}" width="10" height="10" fill="{CREATED_COLOUR}"/><text x="44" y="{text_y}" fill="currentColor">created</text><rect x="110" y="{legend_y}" width="10" height="10" fill="{COMPLETED_COLOUR}"/><text x="124" y="{text_y}" fill="currentColor">done</text></svg>"##,
+        legend_y = bottom + 26,
+        text_y = bottom + 35,
+    );
+    svg
+}
+
+// a ring filled to `percentage`, with the number in the middle
+pub fn ring_chart(percentage: f64, description: &str) -> String {
+    let radius = 40.0;
+    let circumference = 2.0 * std::f64::consts::PI * radius;
+    let filled = circumference * percentage.clamp(0.0, 100.0) / 100.0;
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="120" height="120" viewBox="0 0 120 120" font-family="sans-serif" role="img" aria-label="{label}"><circle cx="60" cy="60" r="{radius}" fill="none" stroke="#888" stroke-opacity="0.3" stroke-width="14"/><circle cx="60" cy="60" r="{radius}" fill="none" stroke="{COMPLETED_COLOUR}" stroke-width="14" stroke-dasharray="{filled:.1} {circumference:.1}" transform="rotate(-90 60 60)"/><text x="60" y="66" text-anchor="middle" font-size="18" fill="currentColor">{percentage:.0}%</text></svg>"##,
+        label = escape_xml(description),
+    )
+}
@@ -0,0 +1,212 @@
+use crate::members::ProjectMember;
+use crate::serialise::serialise_data;
+use rusqlite::{params, Connection, Error, Row};
+use serde::{Deserialize, Serialize};
+
+pub const THREADS_PER_PAGE: usize = 10;
+
+// a top-level comment carries its replies; replies are one level deep
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub id: i64,
+    pub project_id: u8,
+    pub user_id: u8,
+    pub author_email: String,
+    pub parent_id: Option<i64>,
+    pub body: String,
+    pub created_at: String,
+    pub edited_at: Option<String>,
+    pub deleted: bool,
+    pub replies: Vec<Comment>,
+}
+
+const COMMENT_COLUMNS: &str = "comment.id, comment.project_id, comment.user_id, user.email,
+    comment.parent_id, comment.body, comment.created_at, comment.edited_at,
+    comment.deleted_at IS NOT NULL";
+
+fn comment_from_row(row: &Row) -> Result<Comment, Error> {
+    Ok(Comment {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        user_id: row.get(2)?,
+        author_email: row.get(3)?,
+        parent_id: row.get(4)?,
+        body: row.get(5)?,
+        created_at: row.get(6)?,
+        edited_at: row.get(7)?,
+        deleted: row.get(8)?,
+        replies: Vec::new(),
+    })
+}
+
+pub fn count_comment_threads(project_id: u8) -> Result<usize, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT COUNT(*) FROM comment WHERE project_id = ?1 AND parent_id IS NULL",
+        params![project_id],
+        |row| row.get(0),
+    )
+}
+
+// oldest threads first; `page` starts at 1
+pub fn query_comment_threads(project_id: u8, page: usize) -> Result<Vec<Comment>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let offset = page.saturating_sub(1) * THREADS_PER_PAGE;
+
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM comment
+            JOIN user ON comment.user_id = user.id
+            WHERE comment.project_id = ?1 AND comment.parent_id IS NULL
+            ORDER BY comment.id
+            LIMIT ?2 OFFSET ?3",
+        COMMENT_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(
+        params![project_id, THREADS_PER_PAGE, offset],
+        comment_from_row,
+    )?;
+    let mut threads = serialise_data(items_iter);
+
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM comment
+            JOIN user ON comment.user_id = user.id
+            WHERE comment.parent_id IN (
+                SELECT id FROM comment
+                    WHERE project_id = ?1 AND parent_id IS NULL
+                    ORDER BY id
+                    LIMIT ?2 OFFSET ?3)
+            ORDER BY comment.id",
+        COMMENT_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(
+        params![project_id, THREADS_PER_PAGE, offset],
+        comment_from_row,
+    )?;
+    for reply in serialise_data(items_iter) {
+        if let Some(thread) = threads
+            .iter_mut()
+            .find(|thread| Some(thread.id) == reply.parent_id)
+        {
+            thread.replies.push(reply);
+        }
+    }
+
+    Ok(threads)
+}
+
+pub fn query_comment_by_id(project_id: u8, comment_id: i64) -> Result<Comment, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM comment
+                JOIN user ON comment.user_id = user.id
+                WHERE comment.project_id = ?1 AND comment.id = ?2",
+            COMMENT_COLUMNS
+        ),
+        params![project_id, comment_id],
+        comment_from_row,
+    )
+}
+
+// the page of the thread a comment belongs to, for linking straight to it
+pub fn query_comment_page(project_id: u8, comment_id: i64) -> Result<usize, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let earlier_threads: usize = conn.query_row(
+        "SELECT COUNT(*) FROM comment
+            WHERE project_id = ?1 AND parent_id IS NULL
+                AND id < (SELECT COALESCE(parent_id, id) FROM comment WHERE id = ?2)",
+        params![project_id, comment_id],
+        |row| row.get(0),
+    )?;
+    Ok(earlier_threads / THREADS_PER_PAGE + 1)
+}
+
+// a reply to a reply joins the thread of the comment it answers
+pub fn add_comment(
+    project_id: u8,
+    user_id: u8,
+    parent_id: Option<i64>,
+    body: &str,
+) -> Result<i64, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let thread_id = match parent_id {
+        Some(parent_id) => Some(conn.query_row(
+            "SELECT COALESCE(parent_id, id) FROM comment WHERE project_id = ?1 AND id = ?2",
+            params![project_id, parent_id],
+            |row| row.get::<_, i64>(0),
+        )?),
+        None => None,
+    };
+    conn.execute(
+        "INSERT INTO comment (project_id, user_id, parent_id, body) VALUES (?1, ?2, ?3, ?4)",
+        params![project_id, user_id, thread_id, body],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+// only the author can edit; deleted comments stay deleted
+pub fn edit_comment(project_id: u8, comment_id: i64, user_id: u8, body: &str) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let updated = conn.execute(
+        "UPDATE comment SET body = ?1, edited_at = CURRENT_TIMESTAMP
+            WHERE project_id = ?2 AND id = ?3 AND user_id = ?4 AND deleted_at IS NULL",
+        params![body, project_id, comment_id, user_id],
+    )?;
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// the row stays so replies keep their place in the thread
+pub fn delete_comment(project_id: u8, comment_id: i64, user_id: u8) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "UPDATE comment SET body = '', deleted_at = CURRENT_TIMESTAMP
+            WHERE project_id = ?1 AND id = ?2 AND user_id = ?3 AND deleted_at IS NULL",
+        params![project_id, comment_id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// `@someone@example.com`, or just `@someone` when exactly one member is someone@...
+pub fn mentioned_user_ids(body: &str, members: &[ProjectMember]) -> Vec<u8> {
+    let mut mentioned = Vec::new();
+    let words = body
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '(' | ')'))
+        // `**@someone**` is still a mention
+        .map(|word| word.trim_matches(['*', '_', '~']))
+        .filter_map(|word| word.strip_prefix('@'))
+        .map(|word| word.trim_end_matches(['.', '!', '?']));
+
+    for word in words.filter(|word| !word.is_empty()) {
+        let word = word.to_lowercase();
+        let exact = members
+            .iter()
+            .find(|member| member.email.to_lowercase() == word);
+        let by_name: Vec<_> = members
+            .iter()
+            .filter(|member| {
+                member
+                    .email
+                    .split('@')
+                    .next()
+                    .is_some_and(|name| name.to_lowercase() == word)
+            })
+            .collect();
+        let member = match (exact, by_name.as_slice()) {
+            (Some(member), _) => Some(member),
+            (None, [member]) => Some(*member),
+            _ => None,
+        };
+        if let Some(member) = member {
+            if !mentioned.contains(&member.user_id) {
+                mentioned.push(member.user_id);
+            }
+        }
+    }
+    mentioned
+}
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+
+// read from Rocket.toml or ROCKET_* environment variables, e.g. ROCKET_MAIL_BACKEND=file
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AppConfig {
+    pub deadline_reminder_days: i64,
+    pub deadline_check_interval_secs: u64,
+    pub mail_backend: MailBackend,
+    pub mail_dir: String,
+    pub mail_from: String,
+    pub registration_mode: RegistrationMode,
+    pub upload_dir: String,
+    pub max_upload_bytes: u64,
+    pub upload_content_types: Vec<String>,
+    // where the app is reachable, e.g. "https://projects.example.com", for links that leave
+    // the browser; empty means https:// and the Host the request came in on
+    pub base_url: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    None,
+    File,
+}
+
+// who may use /add-user; invitation links only sign people up in `invite_only` and `open`,
+// though existing accounts can join with them in any mode
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    Open,
+    InviteOnly,
+    Closed,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            deadline_reminder_days: 3,
+            deadline_check_interval_secs: 60 * 60,
+            mail_backend: MailBackend::None,
+            mail_dir: "mail".to_string(),
+            mail_from: "rocket-db-forms@localhost".to_string(),
+            registration_mode: RegistrationMode::Open,
+            upload_dir: "uploads".to_string(),
+            max_upload_bytes: 5 * 1024 * 1024,
+            upload_content_types: [
+                "image/png",
+                "image/jpeg",
+                "image/gif",
+                "application/pdf",
+                "text/plain",
+                "text/markdown",
+            ]
+            .map(String::from)
+            .to_vec(),
+            base_url: String::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    // an absolute link to `path`, which starts with a slash
+    pub fn absolute_url(&self, host: &str, path: &str) -> String {
+        if self.base_url.is_empty() {
+            format!("https://{}{}", host, path)
+        } else {
+            format!("{}{}", self.base_url.trim_end_matches('/'), path)
+        }
+    }
+}
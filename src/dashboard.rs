@@ -0,0 +1,205 @@
+use crate::db_queries::{query_projects_page, Project};
+use crate::listing::ListQuery;
+use crate::serialise::{serialise_data, sql_text_enum};
+use crate::tags::query_project_ids_with_tags;
+use rocket::form::{Form, FromForm, FromFormField};
+use rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+// how many projects a list widget shows
+pub const LIST_WIDGET_SIZE: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum WidgetKind {
+    // how many projects match
+    #[field(value = "count")]
+    Count,
+    // the first few of them, in the filter's order
+    #[field(value = "list")]
+    List,
+    // the ones among them past their due date
+    #[field(value = "overdue")]
+    Overdue,
+}
+
+impl WidgetKind {
+    pub const ALL: [WidgetKind; 3] = [WidgetKind::Count, WidgetKind::List, WidgetKind::Overdue];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WidgetKind::Count => "count",
+            WidgetKind::List => "list",
+            WidgetKind::Overdue => "overdue",
+        }
+    }
+}
+
+sql_text_enum!(WidgetKind);
+
+// a filter is kept as the query string /profile was showing when it was saved
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedFilter {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+}
+
+// a widget with what it currently shows
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Widget {
+    pub id: i64,
+    pub kind: WidgetKind,
+    pub filter: SavedFilter,
+    pub count: usize,
+    pub projects: Vec<Project>,
+}
+
+#[derive(FromForm, Debug, Default)]
+struct TagQuery {
+    tag: Vec<String>,
+}
+
+// the list settings and tag names in a saved query string; anything unreadable is ignored
+pub fn saved_query(query: &str) -> (ListQuery, Vec<String>) {
+    let list = Form::<ListQuery>::parse(query).unwrap_or_default();
+    let mut tags = Form::<TagQuery>::parse(query).unwrap_or_default().tag;
+    tags.retain(|name| !name.is_empty());
+    tags.sort();
+    tags.dedup();
+    (list, tags)
+}
+
+pub fn query_saved_filters(user_id: u8, team_id: i64) -> Result<Vec<SavedFilter>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT id, name, query FROM saved_filter
+            WHERE user_id = ?1 AND team_id = ?2
+            ORDER BY name COLLATE NOCASE",
+    )?;
+    let items_iter = statement.query_map(params![user_id, team_id], |row| {
+        Ok(SavedFilter {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            query: row.get(2)?,
+        })
+    })?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// saving under a name that's taken replaces that filter's query
+pub fn save_filter(user_id: u8, team_id: i64, name: &str, query: &str) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO saved_filter (user_id, team_id, name, query) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (user_id, team_id, name) DO UPDATE SET query = excluded.query",
+        params![user_id, team_id, name, query],
+    )?;
+    Ok(())
+}
+
+// takes the filter's widgets with it
+pub fn delete_saved_filter(user_id: u8, filter_id: i64) -> Result<(), Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    let deleted = tx.execute(
+        "DELETE FROM saved_filter WHERE user_id = ?1 AND id = ?2",
+        params![user_id, filter_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    tx.execute(
+        "DELETE FROM dashboard_widget WHERE filter_id = ?1",
+        params![filter_id],
+    )?;
+    tx.commit()
+}
+
+// new widgets go at the end of the dashboard
+pub fn add_widget(user_id: u8, filter_id: i64, kind: WidgetKind) -> Result<i64, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let owned = conn
+        .query_row(
+            "SELECT id FROM saved_filter WHERE user_id = ?1 AND id = ?2",
+            params![user_id, filter_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()?;
+    if owned.is_none() {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    conn.execute(
+        "INSERT INTO dashboard_widget (user_id, filter_id, kind, position)
+            VALUES (?1, ?2, ?3,
+                (SELECT COALESCE(MAX(position), -1) + 1 FROM dashboard_widget WHERE user_id = ?1))",
+        params![user_id, filter_id, kind],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn delete_widget(user_id: u8, widget_id: i64) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "DELETE FROM dashboard_widget WHERE user_id = ?1 AND id = ?2",
+        params![user_id, widget_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// the user's widgets for the workspace, filled in from their own projects
+pub fn query_widgets(user_id: u8, team_id: i64) -> Result<Vec<Widget>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT dashboard_widget.id, dashboard_widget.kind,
+                saved_filter.id, saved_filter.name, saved_filter.query
+            FROM dashboard_widget
+            JOIN saved_filter ON saved_filter.id = dashboard_widget.filter_id
+            WHERE dashboard_widget.user_id = ?1 AND saved_filter.team_id = ?2
+            ORDER BY dashboard_widget.position, dashboard_widget.id",
+    )?;
+    let items_iter = statement.query_map(params![user_id, team_id], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, WidgetKind>(1)?,
+            SavedFilter {
+                id: row.get(2)?,
+                name: row.get(3)?,
+                query: row.get(4)?,
+            },
+        ))
+    })?;
+
+    let mut widgets = Vec::new();
+    for (id, kind, filter) in serialise_data(items_iter) {
+        let (mut list, tags) = saved_query(&filter.query);
+        list.page = None;
+        list.per_page = Some(LIST_WIDGET_SIZE);
+        if kind == WidgetKind::Overdue {
+            list.overdue = Some(true);
+        }
+        let tagged = if tags.is_empty() {
+            None
+        } else {
+            Some(query_project_ids_with_tags(team_id, &tags)?)
+        };
+        let (projects, count) =
+            query_projects_page(team_id, Some(user_id), &list, tagged.as_deref())?;
+        widgets.push(Widget {
+            id,
+            kind,
+            filter,
+            count,
+            projects: if kind == WidgetKind::Count {
+                Vec::new()
+            } else {
+                projects
+            },
+        });
+    }
+    Ok(widgets)
+}
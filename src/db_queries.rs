@@ -1,6 +1,14 @@
+use crate::events::{publish_project_event, ProjectEvent, ProjectEventKind};
+use crate::invitations::redeem_invitation;
+use crate::listing::ListQuery;
 use crate::passwords::hash_password;
-use crate::serialise::{parse_date, serialise_data};
-use rusqlite::{params, Connection, Error};
+use crate::serialise::{parse_date, serialise_data, sql_text_enum};
+use crate::teams::{add_team, insert_team_member};
+use rocket::form::FromFormField;
+use rusqlite::types::Value;
+use rusqlite::{
+    params, params_from_iter, Connection, Error, OptionalExtension, Row, TransactionBehavior,
+};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
@@ -8,6 +16,8 @@ use std::fmt::Debug;
 pub struct User {
     pub id: u8,
     pub email: String,
+    // never sent to templates or JSON responses
+    #[serde(skip_serializing)]
     pub password: String,
     pub admin: bool,
 }
@@ -16,21 +26,164 @@ pub struct Admin {
     pub user: User,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectStatus {
+    #[field(value = "planned")]
+    Planned,
+    #[field(value = "active")]
+    Active,
+    #[field(value = "on_hold")]
+    OnHold,
+    #[field(value = "done")]
+    Done,
+    #[field(value = "cancelled")]
+    Cancelled,
+}
+
+impl ProjectStatus {
+    pub const ALL: [ProjectStatus; 5] = [
+        ProjectStatus::Planned,
+        ProjectStatus::Active,
+        ProjectStatus::OnHold,
+        ProjectStatus::Done,
+        ProjectStatus::Cancelled,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectStatus::Planned => "planned",
+            ProjectStatus::Active => "active",
+            ProjectStatus::OnHold => "on_hold",
+            ProjectStatus::Done => "done",
+            ProjectStatus::Cancelled => "cancelled",
+        }
+    }
+
+    // done and cancelled projects no longer have a deadline to chase
+    pub fn is_closed(&self) -> bool {
+        matches!(self, ProjectStatus::Done | ProjectStatus::Cancelled)
+    }
+}
+
+sql_text_enum!(ProjectStatus);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectPriority {
+    #[field(value = "low")]
+    Low,
+    #[field(value = "medium")]
+    Medium,
+    #[field(value = "high")]
+    High,
+    #[field(value = "urgent")]
+    Urgent,
+}
+
+impl ProjectPriority {
+    pub const ALL: [ProjectPriority; 4] = [
+        ProjectPriority::Low,
+        ProjectPriority::Medium,
+        ProjectPriority::High,
+        ProjectPriority::Urgent,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectPriority::Low => "low",
+            ProjectPriority::Medium => "medium",
+            ProjectPriority::High => "high",
+            ProjectPriority::Urgent => "urgent",
+        }
+    }
+}
+
+sql_text_enum!(ProjectPriority);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectRole {
+    #[field(value = "owner")]
+    Owner,
+    #[field(value = "editor")]
+    Editor,
+    #[field(value = "viewer")]
+    Viewer,
+}
+
+impl ProjectRole {
+    pub const ALL: [ProjectRole; 3] =
+        [ProjectRole::Owner, ProjectRole::Editor, ProjectRole::Viewer];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectRole::Owner => "owner",
+            ProjectRole::Editor => "editor",
+            ProjectRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn can_edit(&self) -> bool {
+        matches!(self, ProjectRole::Owner | ProjectRole::Editor)
+    }
+}
+
+sql_text_enum!(ProjectRole);
+
+// `due_date` is the planned deadline, `end_date` the day the project was actually done
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
     pub id_proj: Option<u8>,
     pub name: String,
     pub start_date: String,
     pub end_date: String,
     pub user_id: u8,
+    pub description: String,
+    pub status: ProjectStatus,
+    pub priority: ProjectPriority,
+    pub due_date: String,
+}
+
+// the fields a user can set when adding or editing a project
+#[derive(Debug)]
+pub struct ProjectInput<'a> {
+    pub name: &'a str,
+    pub description: &'a str,
+    pub status: ProjectStatus,
+    pub priority: ProjectPriority,
+    pub due_date: &'a str,
+    pub end_date: &'a str,
 }
 
-pub fn query_all_users() -> Result<Vec<User>, Error> {
+pub const PROJECT_COLUMNS: &str =
+    "id_proj, name, start_date, end_date, user_id, description, status, priority, due_date";
+
+pub fn project_from_row(row: &Row) -> Result<Project, Error> {
+    Ok(Project {
+        id_proj: row.get(0)?,
+        name: row.get(1)?,
+        start_date: row.get(2)?,
+        end_date: row.get(3)?,
+        user_id: row.get(4)?,
+        description: row.get(5)?,
+        status: row.get(6)?,
+        priority: row.get(7)?,
+        due_date: row.get(8)?,
+    })
+}
+
+// members of the workspace
+pub fn query_all_users(team_id: i64) -> Result<Vec<User>, Error> {
     let conn = Connection::open("db.sqlite")?;
 
-    let mut stmt = conn.prepare("SELECT * FROM user")?;
+    let mut stmt = conn.prepare(
+        "SELECT user.* FROM user
+            JOIN team_member ON team_member.user_id = user.id
+            WHERE team_member.team_id = ?1",
+    )?;
 
-    let items_iter = stmt.query_map([], |row| {
+    let items_iter = stmt.query_map(params![team_id], |row| {
         let id: u8 = row.get(0)?;
         let email: String = row.get::<_, String>(1)?;
         let password: String = row.get::<_, String>(2)?;
@@ -46,27 +199,207 @@ pub fn query_all_users() -> Result<Vec<User>, Error> {
     Ok(serialise_data(items_iter))
 }
 
-pub fn query_all_projects() -> Result<Vec<Project>, Error> {
+pub fn query_all_projects(team_id: i64) -> Result<Vec<Project>, Error> {
     let conn = Connection::open("db.sqlite")?;
-    let mut statement = conn.prepare("SELECT * FROM project")?;
-    let items_iter = statement.query_map([], |row| {
-        let id_proj: Option<u8> = row.get(0)?;
-        let name: String = row.get::<_, String>(1)?;
-        let start_date: String = row.get::<_, String>(2)?;
-        let end_date: String = row.get::<_, String>(3)?;
-        let user_id: u8 = row.get(4)?;
-        Ok(Project {
-            id_proj,
-            name,
-            start_date,
-            end_date,
-            user_id,
-        })
-    })?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM project WHERE team_id = ?1",
+        PROJECT_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![team_id], project_from_row)?;
 
     Ok(serialise_data(items_iter))
 }
 
+// what list pages can sort users by, the default first
+pub const USER_SORTS: &[(&str, &str)] = &[
+    ("id", "user.id"),
+    ("email", "user.email COLLATE NOCASE"),
+    ("admin", "user.admin"),
+];
+
+// one page of the workspace's members, and how many there are in all
+pub fn query_users_page(team_id: i64, list: &ListQuery) -> Result<(Vec<User>, usize), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let total: usize = conn.query_row(
+        "SELECT COUNT(*) FROM team_member WHERE team_id = ?1",
+        params![team_id],
+        |row| row.get(0),
+    )?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT user.id, user.email, user.password, user.admin FROM user
+            JOIN team_member ON team_member.user_id = user.id
+            WHERE team_member.team_id = ?1
+            {}
+            LIMIT ?2 OFFSET ?3",
+        list.order_by(USER_SORTS, "user.id")
+    ))?;
+    let items_iter = statement.query_map(
+        params![team_id, list.per_page(), list.offset(total)],
+        |row| {
+            Ok(User {
+                id: row.get(0)?,
+                email: row.get(1)?,
+                password: row.get(2)?,
+                admin: row.get(3)?,
+            })
+        },
+    )?;
+
+    Ok((serialise_data(items_iter), total))
+}
+
+// site admins among the workspace's members
+pub fn query_admin_count(team_id: i64) -> Result<usize, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT COUNT(*) FROM user
+            JOIN team_member ON team_member.user_id = user.id
+            WHERE team_member.team_id = ?1 AND user.admin",
+        params![team_id],
+        |row| row.get(0),
+    )
+}
+
+// what list pages can sort projects by, the default first
+pub const PROJECT_SORTS: &[(&str, &str)] = &[
+    ("id", "id_proj"),
+    ("name", "name COLLATE NOCASE"),
+    ("start_date", "start_date"),
+    ("end_date", "end_date"),
+    ("due_date", "due_date"),
+    (
+        "status",
+        "CASE status WHEN 'planned' THEN 0 WHEN 'active' THEN 1 WHEN 'on_hold' THEN 2
+            WHEN 'done' THEN 3 ELSE 4 END",
+    ),
+    (
+        "priority",
+        "CASE priority WHEN 'low' THEN 0 WHEN 'medium' THEN 1 WHEN 'high' THEN 2 ELSE 3 END",
+    ),
+];
+
+// done and cancelled, quoted for an `IN (...)`
+fn closed_statuses() -> String {
+    ProjectStatus::ALL
+        .iter()
+        .filter(|status| status.is_closed())
+        .map(|status| format!("'{}'", status.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// the WHERE clause for a filtered project list; `member` narrows it to the projects a user
+// owns or was added to, `project_ids` to a set worked out beforehand (like tagged projects)
+pub fn project_filter(
+    team_id: i64,
+    member: Option<u8>,
+    list: &ListQuery,
+    project_ids: Option<&[u8]>,
+) -> (String, Vec<Value>) {
+    let mut conditions = vec!["team_id = ?".to_string()];
+    let mut values: Vec<Value> = vec![team_id.into()];
+    if let Some(user_id) = member {
+        conditions.push(
+            "(user_id = ? OR id_proj IN (SELECT project_id FROM project_member WHERE user_id = ?))"
+                .to_string(),
+        );
+        values.push(i64::from(user_id).into());
+        values.push(i64::from(user_id).into());
+    }
+    if let Some(status) = list.status {
+        conditions.push("status = ?".to_string());
+        values.push(status.as_str().to_string().into());
+    }
+    if let Some(from) = list.start_from() {
+        conditions.push("start_date >= ?".to_string());
+        values.push(from.into());
+    }
+    if let Some(until) = list.start_until() {
+        conditions.push("start_date < ?".to_string());
+        values.push(until.into());
+    }
+    if let Some(owner) = list.owner {
+        conditions.push("user_id = ?".to_string());
+        values.push(i64::from(owner).into());
+    }
+    if list.overdue == Some(true) {
+        conditions.push(format!(
+            "status NOT IN ({}) AND due_date != '' AND due_date < ?",
+            closed_statuses()
+        ));
+        values.push(
+            chrono::Local::now()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+                .into(),
+        );
+    }
+    if let Some(ids) = project_ids {
+        conditions.push(format!("id_proj IN ({})", vec!["?"; ids.len()].join(", ")));
+        values.extend(ids.iter().map(|id| Value::from(i64::from(*id))));
+    }
+    (conditions.join(" AND "), values)
+}
+
+// one page of the workspace's projects matching the list's filters, and how many match in all
+pub fn query_projects_page(
+    team_id: i64,
+    member: Option<u8>,
+    list: &ListQuery,
+    project_ids: Option<&[u8]>,
+) -> Result<(Vec<Project>, usize), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let (filter, mut values) = project_filter(team_id, member, list, project_ids);
+    let total: usize = conn.query_row(
+        &format!("SELECT COUNT(*) FROM project WHERE {}", filter),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM project WHERE {} {} LIMIT ? OFFSET ?",
+        PROJECT_COLUMNS,
+        filter,
+        list.order_by(PROJECT_SORTS, "id_proj")
+    ))?;
+    values.push((list.per_page() as i64).into());
+    values.push((list.offset(total) as i64).into());
+    let items_iter = statement.query_map(params_from_iter(values), project_from_row)?;
+
+    Ok((serialise_data(items_iter), total))
+}
+
+// how many of the matching projects have each status, and how many of the open ones are overdue
+pub fn query_project_summary(
+    team_id: i64,
+    list: &ListQuery,
+    project_ids: Option<&[u8]>,
+    now: &str,
+) -> Result<(Vec<(ProjectStatus, usize)>, usize), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let (filter, mut values) = project_filter(team_id, None, list, project_ids);
+    let mut statement = conn.prepare(&format!(
+        "SELECT status, COUNT(*) FROM project WHERE {} GROUP BY status",
+        filter
+    ))?;
+    let items_iter = statement.query_map(params_from_iter(values.iter()), |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    let counts = serialise_data(items_iter);
+
+    values.push(now.to_string().into());
+    let overdue = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM project
+                WHERE {} AND status NOT IN ({}) AND due_date != '' AND due_date < ?",
+            filter,
+            closed_statuses()
+        ),
+        params_from_iter(values),
+        |row| row.get(0),
+    )?;
+    Ok((counts, overdue))
+}
+
 pub fn query_user_by_id(id: u8) -> Result<User, Error> {
     let conn = Connection::open("db.sqlite")?;
 
@@ -142,56 +475,28 @@ pub fn query_user_by_email(email: String) -> Result<User, Error> {
     }
 }
 
-pub fn query_all_projects_for_user(user_id: u8) -> Result<Vec<Project>, Error> {
-    let user_id = user_id.to_string();
+// every project in the workspace the user owns or has been added to as a member
+pub fn query_all_projects_for_user(user_id: u8, team_id: i64) -> Result<Vec<Project>, Error> {
     let conn = Connection::open("db.sqlite")?;
-    let mut statement = conn.prepare(
-        format!(
-            "SELECT * FROM project
-                JOIN user ON project.user_id = user.id
-                WHERE user.id = {}",
-            user_id
-        )
-        .as_str(),
-    )?;
-    let items_iter = statement.query_map([], |row| {
-        let id_proj: Option<u8> = row.get(0)?;
-        let name: String = row.get::<_, String>(1)?;
-        let start_date: String = row.get::<_, String>(2)?;
-        let end_date: String = row.get::<_, String>(3)?;
-        let user_id: u8 = row.get(4)?;
-        Ok(Project {
-            id_proj,
-            name,
-            start_date,
-            end_date,
-            user_id,
-        })
-    })?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM project
+            WHERE team_id = ?2
+                AND (user_id = ?1
+                    OR id_proj IN (SELECT project_id FROM project_member WHERE user_id = ?1))",
+        PROJECT_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![user_id, team_id], project_from_row)?;
 
     Ok(serialise_data(items_iter))
 }
 
-pub fn query_project_by_id(id: u8) -> Result<Project, Error> {
-    let id = id.to_string();
+pub fn query_project_by_id(id: u8, team_id: i64) -> Result<Project, Error> {
     let conn = Connection::open("db.sqlite")?;
-    let mut statement =
-        conn.prepare(format!("SELECT * FROM project WHERE id_proj = {}", id).as_str())?;
-    let mut items_iter = statement.query_map([], |row| {
-        let id_proj: Option<u8> = row.get(0)?;
-        let name: String = row.get::<_, String>(1)?;
-        let start_date: String = row.get::<_, String>(2)?;
-        let end_date: String = row.get::<_, String>(3)?;
-        let user_id: u8 = row.get(4)?;
-        let project = Project {
-            id_proj,
-            name,
-            start_date,
-            end_date,
-            user_id,
-        };
-        Ok(project)
-    })?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM project WHERE id_proj = ?1 AND team_id = ?2",
+        PROJECT_COLUMNS
+    ))?;
+    let mut items_iter = statement.query_map(params![id, team_id], project_from_row)?;
     if let Some(project_result) = items_iter.next() {
         project_result
     } else {
@@ -199,61 +504,280 @@ pub fn query_project_by_id(id: u8) -> Result<Project, Error> {
     }
 }
 
-pub fn add_user(email: &str, password: &str) -> Result<(), Error> {
+// invited users join the inviting team; everyone else starts out with a workspace of
+// their own
+pub fn add_user(email: &str, password: &str, invite: Option<&str>) -> Result<u8, Error> {
     match hash_password(password) {
         Ok(password) => {
-            let conn = Connection::open("db.sqlite")?;
-            match conn.execute(
+            let mut conn = Connection::open("db.sqlite")?;
+            let tx = conn.transaction()?;
+            tx.execute(
                 "INSERT INTO user (email, password) VALUES (?1, ?2)",
                 params![email, password],
-            ) {
-                Ok(_) => Ok(()),
-                Err(err) => Err(err),
+            )?;
+            // a wrapped id would hand the invitation to some other account
+            let user_id = u8::try_from(tx.last_insert_rowid())
+                .map_err(|_| Error::IntegralValueOutOfRange(0, tx.last_insert_rowid()))?;
+            match invite {
+                Some(token) => {
+                    let invitation = redeem_invitation(&tx, token, email)?;
+                    insert_team_member(&tx, invitation.team_id, user_id, invitation.role)?;
+                }
+                None => {
+                    add_team(&tx, email, user_id)?;
+                }
             }
+            tx.commit()?;
+            Ok(user_id)
         }
         // this is a bit of a hack, but it's the only way I can think of to convert the error type
         Err(_err) => Err(rusqlite::Error::ExecuteReturnedResults),
     }
 }
 
-pub fn add_project(name: &str, user_id: u8) -> Result<u8, Error> {
-    let conn = Connection::open("db.sqlite")?;
-    conn.execute(
-        "INSERT INTO project (name, end_date, user_id) VALUES (?1, ?2, ?3)",
-        params![name, "", user_id],
+// date inputs come from the datepicker; empty means "not set"
+fn parse_optional_date(date: &str) -> Result<String, Error> {
+    if date.is_empty() {
+        Ok("".to_string())
+    } else {
+        // Convert the error type
+        parse_date(date).map_err(|_| rusqlite::Error::ExecuteReturnedResults)
+    }
+}
+
+pub fn add_project(project: &ProjectInput, user_id: u8, team_id: i64) -> Result<u8, Error> {
+    let due_date = parse_optional_date(project.due_date)?;
+    let end_date = parse_optional_date(project.end_date)?;
+
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO project
+            (name, end_date, user_id, description, status, priority, due_date, team_id,
+                created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CURRENT_TIMESTAMP)",
+        params![
+            project.name,
+            end_date,
+            user_id,
+            project.description,
+            project.status,
+            project.priority,
+            due_date,
+            team_id
+        ],
+    )?;
+    // a wrapped id would make the owner a member of some other project
+    let project_id = u8::try_from(tx.last_insert_rowid())
+        .map_err(|_| Error::IntegralValueOutOfRange(0, tx.last_insert_rowid()))?;
+    tx.execute(
+        "INSERT INTO project_member (project_id, user_id, role) VALUES (?1, ?2, ?3)",
+        params![project_id, user_id, ProjectRole::Owner],
     )?;
+    tx.commit()?;
+
+    publish_project_event(ProjectEvent::new(
+        ProjectEventKind::Created,
+        project_id,
+        team_id,
+        user_id,
+        vec![user_id],
+        project.name,
+    ));
+    Ok(project_id)
+}
 
-    Ok(conn.last_insert_rowid() as u8)
+// a status change the board's column settings don't allow, or a failed update
+#[derive(Debug)]
+pub enum StatusError {
+    WipLimit(ProjectStatus, u32),
+    DatabaseError(Error),
+}
+
+impl std::fmt::Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StatusError::WipLimit(status, limit) => write!(
+                f,
+                "{} is limited to {} projects",
+                status.as_str().replace('_', " "),
+                limit
+            ),
+            StatusError::DatabaseError(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+impl From<Error> for StatusError {
+    fn from(e: Error) -> Self {
+        StatusError::DatabaseError(e)
+    }
+}
+
+// WIP limits hold for the whole workspace, so every project in it counts, whoever can see it;
+// a project staying in its column is never refused
+fn check_wip_limit(
+    conn: &Connection,
+    project_id: u8,
+    team_id: i64,
+    status: ProjectStatus,
+) -> Result<(), StatusError> {
+    let current: ProjectStatus = conn.query_row(
+        "SELECT status FROM project WHERE id_proj = ?1 AND team_id = ?2",
+        params![project_id, team_id],
+        |row| row.get(0),
+    )?;
+    let limit: Option<u32> = conn
+        .query_row(
+            "SELECT wip_limit FROM board_column WHERE team_id = ?1 AND status = ?2",
+            params![team_id, status],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    let Some(limit) = limit.filter(|_| current != status) else {
+        return Ok(());
+    };
+    let count: u32 = conn.query_row(
+        "SELECT COUNT(*) FROM project WHERE team_id = ?1 AND status = ?2",
+        params![team_id, status],
+        |row| row.get(0),
+    )?;
+    if count >= limit {
+        return Err(StatusError::WipLimit(status, limit));
+    }
+    Ok(())
 }
 
 pub fn edit_project(
     project_id: u8,
-    name: &str,
-    end_date: &str,
+    team_id: i64,
+    project: &ProjectInput,
     user: User,
-) -> Result<u8, rusqlite::Error> {
-    let end_date = if !end_date.is_empty() {
-        parse_date(end_date).map_err(|_| rusqlite::Error::ExecuteReturnedResults)?
-    // Convert the error type
-    } else {
-        "".to_string()
-    };
+) -> Result<u8, StatusError> {
+    let due_date = parse_optional_date(project.due_date)?;
+    let mut end_date = parse_optional_date(project.end_date)?;
+    // marking a project as done records when that happened
+    if project.status == ProjectStatus::Done && end_date.is_empty() {
+        end_date = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    }
 
-    let conn = Connection::open("db.sqlite")?;
-    conn.execute(
-        "REPLACE INTO project (id_proj, name, end_date, user_id) VALUES (?1, ?2, ?3, ?4)",
-        params![project_id, name, end_date, user.id],
+    let mut conn = Connection::open("db.sqlite")?;
+    // immediate, so nobody fills the column between counting and updating
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    if !user_project_role(&tx, user.id, project_id)?.is_some_and(|role| role.can_edit()) {
+        return Err(Error::QueryReturnedNoRows.into());
+    }
+    check_wip_limit(&tx, project_id, team_id, project.status)?;
+    let updated = tx.execute(
+        "UPDATE project
+            SET name = ?1, end_date = ?2, description = ?3, status = ?4, priority = ?5,
+                due_date = ?6
+            WHERE id_proj = ?7 AND team_id = ?8",
+        params![
+            project.name,
+            end_date,
+            project.description,
+            project.status,
+            project.priority,
+            due_date,
+            project_id,
+            team_id
+        ],
     )?;
-    Ok(conn.last_insert_rowid() as u8)
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows.into());
+    }
+
+    let owner_id: u8 = tx.query_row(
+        "SELECT user_id FROM project WHERE id_proj = ?1",
+        params![project_id],
+        |row| row.get(0),
+    )?;
+    let member_ids = project_member_ids(&tx, project_id)?;
+    tx.commit()?;
+    publish_project_event(ProjectEvent::new(
+        ProjectEventKind::Edited,
+        project_id,
+        team_id,
+        owner_id,
+        member_ids,
+        project.name,
+    ));
+    Ok(project_id)
 }
 
-pub fn delete_project_by_id(project_id: u8, user: &User) -> Result<(), Error> {
+// moves a project to another status, e.g. from the board; like an edit, finishing it records when
+pub fn update_project_status(
+    project_id: u8,
+    team_id: i64,
+    status: ProjectStatus,
+    user: &User,
+) -> Result<(), StatusError> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    if !user_project_role(&tx, user.id, project_id)?.is_some_and(|role| role.can_edit()) {
+        return Err(Error::QueryReturnedNoRows.into());
+    }
+    check_wip_limit(&tx, project_id, team_id, status)?;
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let updated = tx.execute(
+        "UPDATE project
+            SET status = ?1,
+                end_date = CASE WHEN ?1 = 'done' AND COALESCE(end_date, '') = '' THEN ?2
+                    ELSE end_date END
+            WHERE id_proj = ?3 AND team_id = ?4",
+        params![status, now, project_id, team_id],
+    )?;
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows.into());
+    }
+
+    let (owner_id, name): (u8, String) = tx.query_row(
+        "SELECT user_id, name FROM project WHERE id_proj = ?1",
+        params![project_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let member_ids = project_member_ids(&tx, project_id)?;
+    tx.commit()?;
+    publish_project_event(ProjectEvent::new(
+        ProjectEventKind::Edited,
+        project_id,
+        team_id,
+        owner_id,
+        member_ids,
+        &name,
+    ));
+    Ok(())
+}
+
+pub fn delete_project_by_id(project_id: u8, team_id: i64, user: &User) -> Result<(), Error> {
     let conn = Connection::open("db.sqlite")?;
+    // lets tasks, reminders and other rows that belong to the project cascade
+    conn.pragma_update(None, "foreign_keys", true)?;
     if user_owns_project_by_id(&conn, user.id, project_id)? {
+        let name: String = conn.query_row(
+            "SELECT name FROM project WHERE id_proj = ?1 AND team_id = ?2",
+            params![project_id, team_id],
+            |row| row.get(0),
+        )?;
+        // collected before the memberships cascade away with the project
+        let member_ids = project_member_ids(&conn, project_id)?;
         conn.execute(
             "DELETE FROM project WHERE id_proj = ?1",
             params![project_id],
         )?;
+
+        publish_project_event(ProjectEvent::new(
+            ProjectEventKind::Deleted,
+            project_id,
+            team_id,
+            user.id,
+            member_ids,
+            &name,
+        ));
         Ok(())
     } else {
         Err(Error::QueryReturnedNoRows)
@@ -261,14 +785,39 @@ pub fn delete_project_by_id(project_id: u8, user: &User) -> Result<(), Error> {
 }
 
 fn user_owns_project_by_id(conn: &Connection, user_id: u8, project_id: u8) -> Result<bool, Error> {
-    let mut statement = conn.prepare("SELECT user_id FROM project WHERE id_proj = ?1")?;
-    let mut rows = statement.query(params![project_id])?;
+    Ok(user_project_role(conn, user_id, project_id)? == Some(ProjectRole::Owner))
+}
 
-    if let Some(row) = rows.next()? {
-        let owner_id: u8 = row.get(0)?;
-        if owner_id == user_id {
-            return Ok(true);
-        }
-    }
-    Ok(false)
+// `None` when the user is not a member of the project (or the project doesn't exist);
+// `project.user_id` always counts as the owner
+pub fn user_project_role(
+    conn: &Connection,
+    user_id: u8,
+    project_id: u8,
+) -> Result<Option<ProjectRole>, Error> {
+    let role: Option<Option<ProjectRole>> = conn
+        .query_row(
+            "SELECT CASE WHEN project.user_id = ?1 THEN 'owner' ELSE project_member.role END
+                FROM project
+                LEFT JOIN project_member
+                    ON project_member.project_id = project.id_proj
+                    AND project_member.user_id = ?1
+                WHERE project.id_proj = ?2",
+            params![user_id, project_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(role.flatten())
+}
+
+pub fn query_project_role(user_id: u8, project_id: u8) -> Result<Option<ProjectRole>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    user_project_role(&conn, user_id, project_id)
+}
+
+fn project_member_ids(conn: &Connection, project_id: u8) -> Result<Vec<u8>, Error> {
+    let mut statement = conn.prepare("SELECT user_id FROM project_member WHERE project_id = ?1")?;
+    let items_iter = statement.query_map(params![project_id], |row| row.get(0))?;
+
+    Ok(serialise_data(items_iter))
 }
@@ -0,0 +1,126 @@
+use crate::mail::{Email, Mailer};
+use crate::notifications::add_notification;
+use rusqlite::{params, Connection, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineKind {
+    Upcoming,
+    Overdue,
+}
+
+impl DeadlineKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadlineKind::Upcoming => "upcoming",
+            DeadlineKind::Overdue => "overdue",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DueProject {
+    pub id_proj: u8,
+    pub name: String,
+    pub due_date: String,
+    pub user_id: u8,
+    pub email: String,
+    pub kind: DeadlineKind,
+    pub deadline_in_app: bool,
+    pub deadline_email: bool,
+}
+
+impl DueProject {
+    fn message(&self) -> String {
+        match self.kind {
+            DeadlineKind::Upcoming => {
+                format!("Project '{}' is due on {}", self.name, self.due_date)
+            }
+            DeadlineKind::Overdue => {
+                format!("Project '{}' was due on {}", self.name, self.due_date)
+            }
+        }
+    }
+}
+
+// projects whose due_date is at most `days` away (or already passed) and that
+// have not been reminded about for that due_date and kind yet
+pub fn query_due_projects(conn: &Connection, days: i64) -> Result<Vec<DueProject>, Error> {
+    let mut statement = conn.prepare(
+        "SELECT id_proj, name, due_date, user_id, email, kind, deadline_in_app, deadline_email
+            FROM (
+                SELECT project.id_proj, project.name, project.due_date, project.user_id,
+                    user.email,
+                    CASE WHEN julianday(project.due_date) < julianday('now')
+                        THEN 'overdue' ELSE 'upcoming' END AS kind,
+                    COALESCE(pref.deadline_in_app, 1) AS deadline_in_app,
+                    COALESCE(pref.deadline_email, 0) AS deadline_email
+                FROM project
+                JOIN user ON project.user_id = user.id
+                LEFT JOIN notification_preference pref ON pref.user_id = user.id
+                WHERE project.due_date != ''
+                    AND project.status NOT IN ('done', 'cancelled')
+                    AND julianday(project.due_date) <= julianday('now', ?1)
+            ) due
+            WHERE NOT EXISTS (
+                SELECT 1 FROM deadline_reminder reminder
+                WHERE reminder.project_id = due.id_proj
+                    AND reminder.kind = due.kind
+                    AND reminder.due_date = due.due_date
+            )",
+    )?;
+    let items_iter = statement.query_map(params![format!("+{} days", days)], |row| {
+        let kind: String = row.get(5)?;
+        Ok(DueProject {
+            id_proj: row.get(0)?,
+            name: row.get(1)?,
+            due_date: row.get(2)?,
+            user_id: row.get(3)?,
+            email: row.get(4)?,
+            kind: if kind == "overdue" {
+                DeadlineKind::Overdue
+            } else {
+                DeadlineKind::Upcoming
+            },
+            deadline_in_app: row.get(6)?,
+            deadline_email: row.get(7)?,
+        })
+    })?;
+
+    items_iter.collect()
+}
+
+// sends one reminder per due project and records it so the next run skips it;
+// returns how many projects were handled
+pub fn check_deadlines(days: i64, mailer: &dyn Mailer) -> Result<usize, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let due_projects = query_due_projects(&conn, days)?;
+
+    for project in &due_projects {
+        let message = project.message();
+        if project.deadline_in_app {
+            add_notification(
+                &conn,
+                project.user_id,
+                &message,
+                &format!("/project/{}", project.id_proj),
+            )?;
+        }
+        if project.deadline_email {
+            let email = Email {
+                to: project.email.clone(),
+                subject: format!("Deadline {}: {}", project.kind.as_str(), project.name),
+                body: message.clone(),
+            };
+            if let Err(e) = mailer.send(&email) {
+                println!("Failed to send deadline reminder to {}: {}", email.to, e);
+            }
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO deadline_reminder (project_id, kind, due_date)
+                VALUES (?1, ?2, ?3)",
+            params![project.id_proj, project.kind.as_str(), project.due_date],
+        )?;
+    }
+
+    Ok(due_projects.len())
+}
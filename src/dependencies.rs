@@ -0,0 +1,233 @@
+use crate::db_queries::{project_from_row, Project, PROJECT_COLUMNS};
+use crate::serialise::serialise_data;
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug)]
+pub enum DependencyError {
+    NotFound,
+    // the project names around the loop, starting and ending with the same one
+    Cycle(Vec<String>),
+    DatabaseError(rusqlite::Error),
+}
+
+impl std::fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DependencyError::NotFound => write!(f, "Project not found"),
+            DependencyError::Cycle(names) => {
+                write!(f, "That would make a loop: {}", names.join(" → "))
+            }
+            DependencyError::DatabaseError(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DependencyError {}
+
+impl From<rusqlite::Error> for DependencyError {
+    fn from(e: rusqlite::Error) -> Self {
+        DependencyError::DatabaseError(e)
+    }
+}
+
+// the projects `project_id` is blocked by
+pub fn query_dependencies(project_id: u8) -> Result<Vec<Project>, rusqlite::Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM project
+            JOIN project_dependency ON project_dependency.depends_on_id = project.id_proj
+            WHERE project_dependency.project_id = ?1
+            ORDER BY name",
+        PROJECT_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![project_id], project_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// the projects waiting on `project_id`
+pub fn query_dependents(project_id: u8) -> Result<Vec<Project>, rusqlite::Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM project
+            JOIN project_dependency ON project_dependency.project_id = project.id_proj
+            WHERE project_dependency.depends_on_id = ?1
+            ORDER BY name",
+        PROJECT_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![project_id], project_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// the chain of dependencies leading from `from` to `to`, both included, if there is one
+fn dependency_path(edges: &HashMap<u8, Vec<u8>>, from: u8, to: u8) -> Option<Vec<u8>> {
+    let mut came_from: HashMap<u8, u8> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(current) = queue.pop_front() {
+        if current == to {
+            let mut path = vec![to];
+            while let Some(previous) = came_from.get(path.last()?) {
+                path.push(*previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in edges.get(&current).into_iter().flatten() {
+            if *next != from && !came_from.contains_key(next) {
+                came_from.insert(*next, current);
+                queue.push_back(*next);
+            }
+        }
+    }
+    None
+}
+
+fn project_names(conn: &Connection, team_id: i64) -> Result<HashMap<u8, String>, rusqlite::Error> {
+    let mut statement = conn.prepare("SELECT id_proj, name FROM project WHERE team_id = ?1")?;
+    let items_iter = statement.query_map(params![team_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    Ok(serialise_data(items_iter).into_iter().collect())
+}
+
+// every "blocked by" link in the team, from each project to the ones it waits on
+fn dependency_edges(
+    conn: &Connection,
+    team_id: i64,
+) -> Result<HashMap<u8, Vec<u8>>, rusqlite::Error> {
+    let mut statement = conn.prepare(
+        "SELECT project_dependency.project_id, project_dependency.depends_on_id
+            FROM project_dependency
+            JOIN project ON project_dependency.project_id = project.id_proj
+            WHERE project.team_id = ?1",
+    )?;
+    let items_iter = statement.query_map(params![team_id], |row| {
+        Ok((row.get::<_, u8>(0)?, row.get::<_, u8>(1)?))
+    })?;
+
+    let mut edges: HashMap<u8, Vec<u8>> = HashMap::new();
+    for (from, to) in serialise_data(items_iter) {
+        edges.entry(from).or_default().push(to);
+    }
+    Ok(edges)
+}
+
+// both projects have to be in the team; refuses anything that would close a loop
+pub fn add_dependency(
+    project_id: u8,
+    depends_on_id: u8,
+    team_id: i64,
+) -> Result<(), DependencyError> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+
+    let names = project_names(&tx, team_id)?;
+    if !names.contains_key(&project_id) || !names.contains_key(&depends_on_id) {
+        return Err(DependencyError::NotFound);
+    }
+    let edges = dependency_edges(&tx, team_id)?;
+
+    // the new edge closes a loop if the other project already leads back to this one
+    let cycle = if project_id == depends_on_id {
+        Some(vec![project_id, project_id])
+    } else {
+        dependency_path(&edges, depends_on_id, project_id).map(|path| {
+            let mut cycle = vec![project_id];
+            cycle.extend(path);
+            cycle
+        })
+    };
+    if let Some(cycle) = cycle {
+        return Err(DependencyError::Cycle(
+            cycle.iter().map(|id| names[id].clone()).collect(),
+        ));
+    }
+
+    tx.execute(
+        "INSERT OR IGNORE INTO project_dependency (project_id, depends_on_id) VALUES (?1, ?2)",
+        params![project_id, depends_on_id],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn remove_dependency(project_id: u8, depends_on_id: u8) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "DELETE FROM project_dependency WHERE project_id = ?1 AND depends_on_id = ?2",
+        params![project_id, depends_on_id],
+    )?;
+    if deleted == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// a project can't sensibly finish before the projects it waits on
+pub fn schedule_warnings(project: &Project, dependencies: &[Project]) -> Vec<String> {
+    if project.end_date.is_empty() {
+        return Vec::new();
+    }
+    dependencies
+        .iter()
+        .filter(|dependency| {
+            !dependency.end_date.is_empty() && project.end_date < dependency.end_date
+        })
+        .map(|dependency| {
+            format!(
+                "{} ends {}, before {} which it depends on ends {}",
+                project.name, project.end_date, dependency.name, dependency.end_date
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edges(links: &[(u8, u8)]) -> HashMap<u8, Vec<u8>> {
+        let mut edges: HashMap<u8, Vec<u8>> = HashMap::new();
+        for (from, to) in links {
+            edges.entry(*from).or_default().push(*to);
+        }
+        edges
+    }
+
+    #[test]
+    fn path_follows_the_chain() {
+        let edges = edges(&[(1, 2), (2, 3), (3, 4)]);
+        assert_eq!(dependency_path(&edges, 1, 4), Some(vec![1, 2, 3, 4]));
+        assert_eq!(dependency_path(&edges, 2, 3), Some(vec![2, 3]));
+    }
+
+    #[test]
+    fn path_takes_the_shortest_route() {
+        let edges = edges(&[(1, 2), (2, 3), (3, 5), (1, 4), (4, 5)]);
+        assert_eq!(dependency_path(&edges, 1, 5), Some(vec![1, 4, 5]));
+    }
+
+    #[test]
+    fn no_path_against_the_links() {
+        let edges = edges(&[(1, 2), (2, 3)]);
+        assert_eq!(dependency_path(&edges, 3, 1), None);
+        assert_eq!(dependency_path(&edges, 1, 9), None);
+    }
+
+    #[test]
+    fn existing_loops_dont_hang_the_search() {
+        let edges = edges(&[(1, 2), (2, 3), (3, 2), (3, 1)]);
+        assert_eq!(dependency_path(&edges, 2, 4), None);
+        assert_eq!(dependency_path(&edges, 2, 1), Some(vec![2, 3, 1]));
+    }
+
+    #[test]
+    fn path_back_to_the_start_is_the_cycle() {
+        // what `add_dependency` checks: does the new dependency already lead back?
+        let edges = edges(&[(2, 3), (3, 1)]);
+        let mut cycle = vec![1];
+        cycle.extend(dependency_path(&edges, 2, 1).unwrap());
+        assert_eq!(cycle, vec![1, 2, 3, 1]);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::db_queries::User;
+use crate::teams::Workspace;
+use rocket::tokio::sync::broadcast::{channel, Receiver, Sender};
+use serde::Serialize;
+use std::sync::OnceLock;
+
+// events nobody is listening to are dropped; slow listeners skip what they missed
+const CHANNEL_CAPACITY: usize = 64;
+
+static PROJECT_EVENTS: OnceLock<Sender<ProjectEvent>> = OnceLock::new();
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectEventKind {
+    Created,
+    Edited,
+    Deleted,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProjectEvent {
+    pub kind: ProjectEventKind,
+    pub project_id: u8,
+    pub team_id: i64,
+    pub user_id: u8,
+    // owner and collaborators at the time of the change
+    #[serde(skip)]
+    pub member_ids: Vec<u8>,
+    pub name: String,
+}
+
+impl ProjectEvent {
+    pub fn new(
+        kind: ProjectEventKind,
+        project_id: u8,
+        team_id: i64,
+        user_id: u8,
+        member_ids: Vec<u8>,
+        name: &str,
+    ) -> Self {
+        ProjectEvent {
+            kind,
+            project_id,
+            team_id,
+            user_id,
+            member_ids,
+            name: name.to_string(),
+        }
+    }
+
+    // the SSE event name htmx listens for, e.g. `sse:project-created`
+    pub fn event_name(&self) -> &'static str {
+        match self.kind {
+            ProjectEventKind::Created => "project-created",
+            ProjectEventKind::Edited => "project-edited",
+            ProjectEventKind::Deleted => "project-deleted",
+        }
+    }
+
+    // same rule as the pages: only the active workspace, where members see their
+    // projects and admins see everything
+    pub fn visible_to(&self, user: &User, workspace: &Workspace) -> bool {
+        self.team_id == workspace.id
+            && (user.admin
+                || workspace.is_admin()
+                || user.id == self.user_id
+                || self.member_ids.contains(&user.id))
+    }
+}
+
+fn project_events() -> &'static Sender<ProjectEvent> {
+    PROJECT_EVENTS.get_or_init(|| channel(CHANNEL_CAPACITY).0)
+}
+
+pub fn publish_project_event(event: ProjectEvent) {
+    // an error only means there are no subscribers right now
+    let _ = project_events().send(event);
+}
+
+pub fn subscribe_project_events() -> Receiver<ProjectEvent> {
+    project_events().subscribe()
+}
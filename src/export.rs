@@ -0,0 +1,192 @@
+use crate::db_queries::{
+    project_filter, project_from_row, User, PROJECT_COLUMNS, PROJECT_SORTS, USER_SORTS,
+};
+use crate::listing::ListQuery;
+use crate::time_entries::csv_field;
+use rocket::form::FromFormField;
+use rocket::http::ContentType;
+use rocket::serde::json;
+use rocket::tokio::sync::mpsc::{channel, Receiver, Sender};
+use rocket::tokio::task::spawn_blocking;
+use rusqlite::{params, params_from_iter, Connection, Error};
+use serde::{Deserialize, Serialize};
+
+// the response is handed over in pieces of about this many bytes
+const CHUNK_SIZE: usize = 16 * 1024;
+// pieces waiting for the client before the query pauses
+const CHUNKS_BUFFERED: usize = 4;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    #[field(value = "csv")]
+    Csv,
+    #[field(value = "json")]
+    Json,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn content_type(&self) -> ContentType {
+        match self {
+            ExportFormat::Csv => ContentType::CSV,
+            ExportFormat::Json => ContentType::JSON,
+        }
+    }
+}
+
+// collects rows into chunks and hands them to the response as they fill up
+struct ChunkSender {
+    format: ExportFormat,
+    sender: Sender<String>,
+    buffer: String,
+    rows: usize,
+}
+
+impl ChunkSender {
+    fn new(format: ExportFormat, sender: Sender<String>, csv_header: &str) -> Self {
+        let buffer = match format {
+            ExportFormat::Csv => format!("{}\n", csv_header),
+            ExportFormat::Json => "[".to_string(),
+        };
+        ChunkSender {
+            format,
+            sender,
+            buffer,
+            rows: 0,
+        }
+    }
+
+    // false once the client has gone away and there's no point reading further
+    fn row<T: Serialize>(&mut self, item: &T, fields: &[String]) -> bool {
+        match self.format {
+            ExportFormat::Csv => {
+                let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                self.buffer.push_str(&line.join(","));
+                self.buffer.push('\n');
+            }
+            ExportFormat::Json => {
+                if self.rows > 0 {
+                    self.buffer.push(',');
+                }
+                self.buffer
+                    .push_str(&json::to_string(item).unwrap_or_else(|_| "null".to_string()));
+            }
+        }
+        self.rows += 1;
+        self.buffer.len() < CHUNK_SIZE || self.flush()
+    }
+
+    fn flush(&mut self) -> bool {
+        self.sender
+            .blocking_send(std::mem::take(&mut self.buffer))
+            .is_ok()
+    }
+
+    fn finish(mut self) {
+        if self.format == ExportFormat::Json {
+            self.buffer.push(']');
+        }
+        self.flush();
+    }
+}
+
+// runs `write` on a blocking thread, the chunks it sends arrive on the receiver;
+// a failed query ends the export early, there's no way to change the status by then
+fn stream_export<F>(format: ExportFormat, csv_header: &'static str, write: F) -> Receiver<String>
+where
+    F: FnOnce(&mut ChunkSender) -> Result<(), Error> + Send + 'static,
+{
+    let (sender, receiver) = channel(CHUNKS_BUFFERED);
+    spawn_blocking(move || {
+        let mut chunks = ChunkSender::new(format, sender, csv_header);
+        match write(&mut chunks) {
+            Ok(()) => chunks.finish(),
+            Err(e) => println!("Export failed after {} rows: {}", chunks.rows, e),
+        }
+    });
+    receiver
+}
+
+// the workspace's projects matching the list's filters, in its order, with no paging;
+// `member` and `project_ids` narrow them down as for `query_projects_page`
+pub fn export_projects(
+    team_id: i64,
+    member: Option<u8>,
+    list: ListQuery,
+    project_ids: Option<Vec<u8>>,
+    format: ExportFormat,
+) -> Receiver<String> {
+    stream_export(
+        format,
+        "id,name,status,priority,start_date,due_date,end_date,owner_id,description",
+        move |chunks| {
+            let conn = Connection::open("db.sqlite")?;
+            let (filter, values) = project_filter(team_id, member, &list, project_ids.as_deref());
+            let mut statement = conn.prepare(&format!(
+                "SELECT {} FROM project WHERE {} {}",
+                PROJECT_COLUMNS,
+                filter,
+                list.order_by(PROJECT_SORTS, "id_proj")
+            ))?;
+            let mut rows = statement.query(params_from_iter(values))?;
+            while let Some(row) = rows.next()? {
+                let project = project_from_row(row)?;
+                let fields = [
+                    project.id_proj.unwrap_or_default().to_string(),
+                    project.name.clone(),
+                    project.status.as_str().to_string(),
+                    project.priority.as_str().to_string(),
+                    project.start_date.clone(),
+                    project.due_date.clone(),
+                    project.end_date.clone(),
+                    project.user_id.to_string(),
+                    project.description.clone(),
+                ];
+                if !chunks.row(&project, &fields) {
+                    break;
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+// the workspace's members in the list's order; password hashes never leave the database
+pub fn export_users(team_id: i64, list: ListQuery, format: ExportFormat) -> Receiver<String> {
+    stream_export(format, "id,email,admin", move |chunks| {
+        let conn = Connection::open("db.sqlite")?;
+        let mut statement = conn.prepare(&format!(
+            "SELECT user.id, user.email, user.admin FROM user
+                JOIN team_member ON team_member.user_id = user.id
+                WHERE team_member.team_id = ?1
+                {}",
+            list.order_by(USER_SORTS, "user.id")
+        ))?;
+        let mut rows = statement.query(params![team_id])?;
+        while let Some(row) = rows.next()? {
+            let user = User {
+                id: row.get(0)?,
+                email: row.get(1)?,
+                password: String::new(),
+                admin: row.get(2)?,
+            };
+            let fields = [
+                user.id.to_string(),
+                user.email.clone(),
+                user.admin.to_string(),
+            ];
+            if !chunks.row(&user, &fields) {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,220 @@
+use crate::db_queries::{Project, ProjectStatus};
+use crate::milestones::Milestone;
+use chrono::{Datelike, Duration, NaiveDate};
+use rocket::form::FromFormField;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+const LABEL_WIDTH: i64 = 200;
+const HEADER_HEIGHT: i64 = 40;
+const ROW_HEIGHT: i64 = 28;
+const BAR_HEIGHT: i64 = 16;
+
+// how much time one screen of the timeline covers
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum Zoom {
+    #[field(value = "week")]
+    Week,
+    #[field(value = "month")]
+    Month,
+    #[field(value = "quarter")]
+    Quarter,
+}
+
+impl Zoom {
+    pub const ALL: [Zoom; 3] = [Zoom::Week, Zoom::Month, Zoom::Quarter];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Zoom::Week => "week",
+            Zoom::Month => "month",
+            Zoom::Quarter => "quarter",
+        }
+    }
+
+    fn pixels_per_day(&self) -> i64 {
+        match self {
+            Zoom::Week => 24,
+            Zoom::Month => 6,
+            Zoom::Quarter => 2,
+        }
+    }
+
+    // where the grid lines go, and what they're labelled
+    fn tick(&self, date: NaiveDate) -> Option<String> {
+        match self {
+            Zoom::Week if date.weekday().num_days_from_monday() == 0 => {
+                Some(date.format("%d %b").to_string())
+            }
+            Zoom::Month if date.day() == 1 => Some(date.format("%b %Y").to_string()),
+            Zoom::Quarter if date.day() == 1 && date.month0().is_multiple_of(3) => {
+                Some(format!("Q{} {}", date.month0() / 3 + 1, date.year()))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn status_colour(status: ProjectStatus) -> &'static str {
+    match status {
+        ProjectStatus::Planned => "#8e9aaf",
+        ProjectStatus::Active => "#1e88e5",
+        ProjectStatus::OnHold => "#f9a825",
+        ProjectStatus::Done => "#43a047",
+        ProjectStatus::Cancelled => "#b0413e",
+    }
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// the day part of "2020-01-01 00:00:00"
+pub fn day(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()
+}
+
+// a project runs from its start to its end date, or its due date when it hasn't ended;
+// with neither it is still open and runs up to today
+fn project_span(project: &Project, today: NaiveDate) -> Option<(NaiveDate, NaiveDate, bool)> {
+    let start = day(&project.start_date)?;
+    let (end, open) = match (day(&project.end_date), day(&project.due_date)) {
+        (Some(end), _) => (end, false),
+        (None, Some(due)) => (due, false),
+        (None, None) => (today.max(start), true),
+    };
+    Some((start, end.max(start), open))
+}
+
+// the projects as bars on a calendar, their milestones as diamonds, and a line for today
+pub fn render_gantt(
+    projects: &[Project],
+    milestones: &[Milestone],
+    zoom: Zoom,
+    today: NaiveDate,
+) -> String {
+    let rows: Vec<_> = projects
+        .iter()
+        .filter_map(|project| Some((project, project_span(project, today)?)))
+        .collect();
+    let scale = zoom.pixels_per_day();
+
+    // a little room on either side so bars don't touch the edges
+    let first = rows
+        .iter()
+        .map(|(_, (start, _, _))| *start)
+        .chain(milestones.iter().filter_map(|m| day(&m.due_date)))
+        .chain([today])
+        .min()
+        .unwrap_or(today)
+        - Duration::days(7);
+    let last = rows
+        .iter()
+        .map(|(_, (_, end, _))| *end)
+        .chain(milestones.iter().filter_map(|m| day(&m.due_date)))
+        .chain([today])
+        .max()
+        .unwrap_or(today)
+        + Duration::days(7);
+    let x = |date: NaiveDate| LABEL_WIDTH + (date - first).num_days() * scale;
+
+    let width = x(last) + scale;
+    let height = HEADER_HEIGHT + ROW_HEIGHT * (rows.len() as i64).max(1);
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="12" role="img" aria-label="project timeline">"##
+    );
+
+    // grid
+    let mut date = first;
+    while date <= last {
+        if let Some(label) = zoom.tick(date) {
+            let _ = write!(
+                svg,
+                r##"<line x1="{x}" y1="{top}" x2="{x}" y2="{height}" stroke="#888" stroke-opacity="0.3"/><text x="{label_x}" y="{label_y}" fill="#888">{label}</text>"##,
+                x = x(date),
+                top = HEADER_HEIGHT - 16,
+                label_x = x(date) + 3,
+                label_y = HEADER_HEIGHT - 20,
+            );
+        }
+        date += Duration::days(1);
+    }
+
+    for (row, (project, (start, end, open))) in rows.iter().enumerate() {
+        let top = HEADER_HEIGHT + ROW_HEIGHT * row as i64;
+        let bar_top = top + (ROW_HEIGHT - BAR_HEIGHT) / 2;
+        let name = escape_xml(&project.name);
+        // long names would run into the bars
+        let label = if project.name.chars().count() > 28 {
+            escape_xml(&format!(
+                "{}…",
+                project.name.chars().take(27).collect::<String>()
+            ))
+        } else {
+            name.clone()
+        };
+        let id = project.id_proj.unwrap_or_default();
+        let _ = write!(
+            svg,
+            r##"<a href="/project/{id}"><text x="8" y="{text_y}" fill="currentColor">{label}</text><rect x="{bar_x}" y="{bar_top}" width="{bar_width}" height="{BAR_HEIGHT}" rx="3" fill="{colour}"{open}><title>{name}: {start} to {end_label} ({status})</title></rect></a>"##,
+            text_y = top + ROW_HEIGHT / 2 + 4,
+            bar_x = x(*start),
+            bar_width = ((*end - *start).num_days() + 1) * scale,
+            colour = status_colour(project.status),
+            open = if *open {
+                r##" fill-opacity="0.5" stroke-dasharray="4 2" stroke="currentColor""##
+            } else {
+                ""
+            },
+            end_label = if *open {
+                "open".to_string()
+            } else {
+                end.to_string()
+            },
+            status = project.status.as_str().replace('_', " "),
+        );
+        for milestone in milestones
+            .iter()
+            .filter(|milestone| Some(milestone.project_id) == project.id_proj)
+        {
+            let Some(due) = day(&milestone.due_date) else {
+                continue;
+            };
+            let centre_x = x(due) + scale / 2;
+            let centre_y = top + ROW_HEIGHT / 2;
+            let _ = write!(
+                svg,
+                r##"<path d="M{centre_x} {top_y} L{right} {centre_y} L{centre_x} {bottom_y} L{left} {centre_y} Z" fill="{fill}" stroke="currentColor"><title>{title}: {due}{reached}</title></path>"##,
+                top_y = centre_y - 7,
+                bottom_y = centre_y + 7,
+                left = centre_x - 7,
+                right = centre_x + 7,
+                fill = if milestone.completed_at.is_some() {
+                    "#43a047"
+                } else {
+                    "#ffffff"
+                },
+                title = escape_xml(&milestone.title),
+                reached = if milestone.completed_at.is_some() {
+                    " (reached)"
+                } else {
+                    ""
+                },
+            );
+        }
+    }
+
+    let _ = write!(
+        svg,
+        r##"<line x1="{today_x}" y1="{top}" x2="{today_x}" y2="{height}" stroke="#e53935" stroke-width="2"><title>today, {today}</title></line></svg>"##,
+        today_x = x(today) + scale / 2,
+        top = HEADER_HEIGHT - 16,
+    );
+    svg
+}
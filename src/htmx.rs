@@ -0,0 +1,19 @@
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Outcome, Request};
+
+// present when the request was sent by htmx, so handlers can answer with a
+// template fragment instead of a full page or a redirect
+#[derive(Debug)]
+pub struct HxRequest;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for HxRequest {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<HxRequest, Self::Error> {
+        match request.headers().get_one("HX-Request") {
+            Some("true") => Outcome::Success(HxRequest),
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
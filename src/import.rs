@@ -0,0 +1,584 @@
+use crate::db_queries::{ProjectPriority, ProjectRole, ProjectStatus};
+use crate::events::{publish_project_event, ProjectEvent, ProjectEventKind};
+use crate::invitations::{insert_invitation, InvitationInput, EMAILED_INVITATION_DAYS};
+use crate::serialise::parse_date;
+use crate::teams::{query_team_members, TeamRole};
+use rocket::form::FromFormField;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// more than this in one file is better split up; project ids are a u8 everywhere, so a
+// bigger file couldn't be imported anyway
+pub const MAX_IMPORT_ROWS: usize = u8::MAX as usize;
+
+pub const PROJECT_COLUMNS: [&str; 8] = [
+    "name",
+    "description",
+    "status",
+    "priority",
+    "start_date",
+    "due_date",
+    "end_date",
+    "owner",
+];
+pub const USER_COLUMNS: [&str; 2] = ["email", "role"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportKind {
+    #[field(value = "projects")]
+    Projects,
+    #[field(value = "users")]
+    Users,
+}
+
+impl ImportKind {
+    pub const ALL: [ImportKind; 2] = [ImportKind::Projects, ImportKind::Users];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportKind::Projects => "projects",
+            ImportKind::Users => "users",
+        }
+    }
+
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            ImportKind::Projects => &PROJECT_COLUMNS,
+            ImportKind::Users => &USER_COLUMNS,
+        }
+    }
+}
+
+// problems with the file as a whole; problems with single rows end up in the preview
+#[derive(Debug)]
+pub enum ImportError {
+    NotText,
+    NoRows,
+    TooManyRows(usize),
+    MissingColumn(&'static str),
+    OutOfIds,
+    DatabaseError(rusqlite::Error),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImportError::NotText => write!(f, "The file isn't UTF-8 text"),
+            ImportError::NoRows => write!(f, "The file has a header but no rows"),
+            ImportError::TooManyRows(rows) => write!(
+                f,
+                "The file has {} rows, split it into files of at most {}",
+                rows, MAX_IMPORT_ROWS
+            ),
+            ImportError::MissingColumn(column) => {
+                write!(f, "The header row has no '{}' column", column)
+            }
+            ImportError::OutOfIds => write!(
+                f,
+                "There's no room for that many more projects, nothing was imported"
+            ),
+            ImportError::DatabaseError(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<rusqlite::Error> for ImportError {
+    fn from(e: rusqlite::Error) -> Self {
+        ImportError::DatabaseError(e)
+    }
+}
+
+// a row of the file as the preview shows it, `line` counting the header as 1
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreviewRow {
+    pub line: usize,
+    pub values: Vec<String>,
+    // what importing the row would do
+    pub note: String,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportPreview {
+    pub kind: ImportKind,
+    pub columns: Vec<String>,
+    pub rows: Vec<PreviewRow>,
+    pub error_count: usize,
+}
+
+impl ImportPreview {
+    fn new(kind: ImportKind, rows: Vec<PreviewRow>) -> Self {
+        ImportPreview {
+            kind,
+            columns: kind.columns().iter().map(|name| name.to_string()).collect(),
+            error_count: rows.iter().filter(|row| !row.errors.is_empty()).count(),
+            rows,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.error_count == 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedProject {
+    pub name: String,
+    pub description: String,
+    pub status: ProjectStatus,
+    pub priority: ProjectPriority,
+    // None starts the project now
+    pub start_date: Option<String>,
+    pub due_date: String,
+    pub end_date: String,
+    pub owner_id: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedUser {
+    pub email: String,
+    pub role: TeamRole,
+}
+
+// the single-use invitation a users import sent someone
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportedInvitation {
+    pub email: String,
+    pub token: String,
+}
+
+// RFC 4180: quoted fields may hold commas, line breaks and doubled quotes;
+// yields the line each record starts on and its fields, leaving out blank lines
+fn parse_csv(text: &str) -> Vec<(usize, Vec<String>)> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+    records.retain(|(_, record)| record.iter().any(|field| !field.trim().is_empty()));
+    records
+}
+
+// undoes the quote `csv_field` puts in front of values a spreadsheet would run as a formula
+fn cell(value: &str) -> String {
+    let value = value.trim();
+    match value.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(['=', '+', '-', '@']) => rest.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+// the file's rows with their values in `columns` order, matched up by the header row;
+// columns the import doesn't know, like the exports' ids, are left out
+fn read_rows(
+    csv: &str,
+    columns: &[&'static str],
+    required: &'static str,
+) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+    let mut records = parse_csv(csv).into_iter();
+    let Some((_, header)) = records.next() else {
+        return Err(ImportError::NoRows);
+    };
+    let header: Vec<String> = header
+        .iter()
+        .map(|name| cell(name).to_lowercase())
+        .collect();
+    if !header.iter().any(|name| name == required) {
+        return Err(ImportError::MissingColumn(required));
+    }
+    let positions: Vec<Option<usize>> = columns
+        .iter()
+        .map(|column| header.iter().position(|name| name == column))
+        .collect();
+    let rows: Vec<(usize, Vec<String>)> = records
+        .map(|(line, record)| {
+            let values = positions
+                .iter()
+                .map(|position| {
+                    position
+                        .and_then(|index| record.get(index))
+                        .map(|value| cell(value))
+                        .unwrap_or_default()
+                })
+                .collect();
+            (line, values)
+        })
+        .collect();
+    match rows.len() {
+        0 => Err(ImportError::NoRows),
+        count if count > MAX_IMPORT_ROWS => Err(ImportError::TooManyRows(count)),
+        _ => Ok(rows),
+    }
+}
+
+// `parse_date` wants the datepicker's `2024-05-01T09:00`;
+// a bare day and the exports' `2024-05-01 09:00:00` are fine too
+fn import_date(value: &str) -> Result<String, ()> {
+    let value = value.replacen(' ', "T", 1);
+    if value.len() == 10 {
+        parse_date(&format!("{}T00:00", value))
+    } else {
+        parse_date(&value)
+    }
+}
+
+fn optional_date(value: &str, column: &str, errors: &mut Vec<String>) -> String {
+    if value.is_empty() {
+        return String::new();
+    }
+    import_date(value).unwrap_or_else(|_| {
+        errors.push(format!(
+            "{} '{}' isn't a date like 2024-05-01 or 2024-05-01T09:00",
+            column, value
+        ));
+        String::new()
+    })
+}
+
+// one of `options` by name, ignoring case, spaces standing in for underscores
+fn choice<T: Copy>(value: &str, options: &[T], name: impl Fn(&T) -> &'static str) -> Option<T> {
+    let value = value.to_lowercase().replace(' ', "_");
+    options.iter().find(|option| name(option) == value).copied()
+}
+
+fn expected<T>(options: &[T], name: impl Fn(&T) -> &'static str) -> String {
+    options.iter().map(name).collect::<Vec<_>>().join(", ")
+}
+
+fn valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+// checks every row of a projects file; owners are looked up among the workspace's members
+// by email, rows without one go to `default_owner`
+pub fn preview_projects(
+    team_id: i64,
+    default_owner: u8,
+    csv: &str,
+) -> Result<(ImportPreview, Vec<ImportedProject>), ImportError> {
+    let rows = read_rows(csv, &PROJECT_COLUMNS, "name")?;
+    let members: HashMap<String, (u8, String)> = query_team_members(team_id)?
+        .into_iter()
+        .map(|member| (member.email.to_lowercase(), (member.user_id, member.email)))
+        .collect();
+    let default_email = members
+        .values()
+        .find(|(user_id, _)| *user_id == default_owner)
+        .map(|(_, email)| email.clone())
+        .unwrap_or_default();
+
+    let mut preview_rows = Vec::new();
+    let mut projects = Vec::new();
+    for (line, values) in rows {
+        let [name, description, status, priority, start_date, due_date, end_date, owner] =
+            <[String; 8]>::try_from(values.clone()).unwrap_or_default();
+        let mut errors = Vec::new();
+        if name.is_empty() {
+            errors.push("name is missing".to_string());
+        }
+        let status = if status.is_empty() {
+            Some(ProjectStatus::Planned)
+        } else {
+            choice(&status, &ProjectStatus::ALL, ProjectStatus::as_str)
+        }
+        .unwrap_or_else(|| {
+            errors.push(format!(
+                "status '{}' isn't one of {}",
+                status,
+                expected(&ProjectStatus::ALL, ProjectStatus::as_str)
+            ));
+            ProjectStatus::Planned
+        });
+        let priority = if priority.is_empty() {
+            Some(ProjectPriority::Medium)
+        } else {
+            choice(&priority, &ProjectPriority::ALL, ProjectPriority::as_str)
+        }
+        .unwrap_or_else(|| {
+            errors.push(format!(
+                "priority '{}' isn't one of {}",
+                priority,
+                expected(&ProjectPriority::ALL, ProjectPriority::as_str)
+            ));
+            ProjectPriority::Medium
+        });
+        let start = optional_date(&start_date, "start_date", &mut errors);
+        let due_date = optional_date(&due_date, "due_date", &mut errors);
+        let end_date = optional_date(&end_date, "end_date", &mut errors);
+        let (owner_id, owner_email) = if owner.is_empty() {
+            (default_owner, default_email.clone())
+        } else {
+            match members.get(&owner.to_lowercase()) {
+                Some((user_id, email)) => (*user_id, email.clone()),
+                None => {
+                    errors.push(format!("owner {} isn't in this workspace", owner));
+                    (default_owner, String::new())
+                }
+            }
+        };
+
+        if errors.is_empty() {
+            projects.push(ImportedProject {
+                name,
+                description,
+                status,
+                priority,
+                start_date: (!start.is_empty()).then_some(start),
+                due_date,
+                end_date,
+                owner_id,
+            });
+        }
+        preview_rows.push(PreviewRow {
+            line,
+            values,
+            note: format!("new project owned by {}", owner_email),
+            errors,
+        });
+    }
+    Ok((
+        ImportPreview::new(ImportKind::Projects, preview_rows),
+        projects,
+    ))
+}
+
+// all of them or none; returns the new projects' ids
+pub fn import_projects(team_id: i64, projects: &[ImportedProject]) -> Result<Vec<u8>, ImportError> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    let mut ids = Vec::new();
+    for project in projects {
+        tx.execute(
+            "INSERT INTO project (name, description, status, priority, start_date, due_date,
+                    end_date, user_id, team_id, created_at)
+                VALUES (?1, ?2, ?3, ?4, COALESCE(?5, CURRENT_TIMESTAMP), ?6, ?7, ?8, ?9,
+                    CURRENT_TIMESTAMP)",
+            params![
+                project.name,
+                project.description,
+                project.status,
+                project.priority,
+                project.start_date,
+                project.due_date,
+                project.end_date,
+                project.owner_id,
+                team_id
+            ],
+        )?;
+        // a wrapped id would make the owner a member of some other project
+        let project_id = u8::try_from(tx.last_insert_rowid()).map_err(|_| ImportError::OutOfIds)?;
+        tx.execute(
+            "INSERT INTO project_member (project_id, user_id, role) VALUES (?1, ?2, ?3)",
+            params![project_id, project.owner_id, ProjectRole::Owner],
+        )?;
+        ids.push(project_id);
+    }
+    tx.commit()?;
+
+    for (project, project_id) in projects.iter().zip(&ids) {
+        publish_project_event(ProjectEvent::new(
+            ProjectEventKind::Created,
+            *project_id,
+            team_id,
+            project.owner_id,
+            vec![project.owner_id],
+            &project.name,
+        ));
+    }
+    Ok(ids)
+}
+
+// checks every row of a users file; everyone gets an invitation rather than being added
+// outright, and whether they have an account yet isn't shown, so it can't be probed for
+pub fn preview_users(
+    team_id: i64,
+    csv: &str,
+) -> Result<(ImportPreview, Vec<ImportedUser>), ImportError> {
+    let rows = read_rows(csv, &USER_COLUMNS, "email")?;
+    let members: Vec<String> = query_team_members(team_id)?
+        .into_iter()
+        .map(|member| member.email.to_lowercase())
+        .collect();
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut preview_rows = Vec::new();
+    let mut users = Vec::new();
+    for (line, values) in rows {
+        let [email, role] = <[String; 2]>::try_from(values.clone()).unwrap_or_default();
+        let mut errors = Vec::new();
+        let key = email.to_lowercase();
+        if email.is_empty() {
+            errors.push("email is missing".to_string());
+        } else if !valid_email(&email) {
+            errors.push(format!("'{}' isn't an email address", email));
+        } else if let Some(first) = seen.get(&key) {
+            errors.push(format!("{} is already on line {}", email, first));
+        } else if members.contains(&key) {
+            errors.push(format!("{} is already in this workspace", email));
+        } else {
+            seen.insert(key, line);
+        }
+        let role = if role.is_empty() {
+            Some(TeamRole::Member)
+        } else {
+            choice(&role, &TeamRole::ALL, TeamRole::as_str)
+        }
+        .unwrap_or_else(|| {
+            errors.push(format!(
+                "role '{}' isn't one of {}",
+                role,
+                expected(&TeamRole::ALL, TeamRole::as_str)
+            ));
+            TeamRole::Member
+        });
+
+        if errors.is_empty() {
+            users.push(ImportedUser { email, role });
+        }
+        preview_rows.push(PreviewRow {
+            line,
+            values,
+            note: format!("invited to join as {}", role.as_str()),
+            errors,
+        });
+    }
+    Ok((ImportPreview::new(ImportKind::Users, preview_rows), users))
+}
+
+// all of them or none; each address gets its own single-use invitation, so signing up still
+// goes through the registration mode's checks
+pub fn import_users(
+    team_id: i64,
+    created_by: u8,
+    users: &[ImportedUser],
+) -> Result<Vec<ImportedInvitation>, ImportError> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    let mut invitations = Vec::new();
+    for user in users {
+        let invitation = InvitationInput {
+            role: user.role,
+            expires_in_days: EMAILED_INVITATION_DAYS,
+            max_uses: Some(1),
+            email: Some(user.email.clone()),
+        };
+        invitations.push(ImportedInvitation {
+            email: user.email.clone(),
+            token: insert_invitation(&tx, team_id, created_by, &invitation)?,
+        });
+    }
+    tx.commit()?;
+    Ok(invitations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(line: usize, fields: &[&str]) -> (usize, Vec<String>) {
+        (line, fields.iter().map(|field| field.to_string()).collect())
+    }
+
+    #[test]
+    fn splits_plain_records() {
+        assert_eq!(
+            parse_csv("name,status\nLaunch,active\n"),
+            vec![row(1, &["name", "status"]), row(2, &["Launch", "active"])]
+        );
+    }
+
+    #[test]
+    fn quoted_fields_keep_commas_and_newlines() {
+        let records = parse_csv("name,description\n\"Launch, v2\",\"first\nsecond\"\nNext,\n");
+        assert_eq!(
+            records,
+            vec![
+                row(1, &["name", "description"]),
+                row(2, &["Launch, v2", "first\nsecond"]),
+                // numbered by where it starts in the file, past the quoted newline
+                row(4, &["Next", ""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn doubled_quotes_are_one_quote() {
+        assert_eq!(
+            parse_csv("\"say \"\"hi\"\"\",\"\"\"\"\n"),
+            vec![row(1, &["say \"hi\"", "\""])]
+        );
+    }
+
+    #[test]
+    fn byte_order_mark_and_crlf_are_ignored() {
+        assert_eq!(
+            parse_csv("\u{feff}email,role\r\nb@x.io,admin\r\n"),
+            vec![row(1, &["email", "role"]), row(2, &["b@x.io", "admin"])]
+        );
+    }
+
+    #[test]
+    fn blank_lines_are_skipped_but_counted() {
+        assert_eq!(
+            parse_csv("email\n\n , \nb@x.io\n"),
+            vec![row(1, &["email"]), row(4, &["b@x.io"])]
+        );
+    }
+
+    #[test]
+    fn last_record_needs_no_newline() {
+        assert_eq!(
+            parse_csv("email,role\nb@x.io,"),
+            vec![row(1, &["email", "role"]), row(2, &["b@x.io", ""])]
+        );
+        assert_eq!(parse_csv(""), Vec::<(usize, Vec<String>)>::new());
+    }
+}
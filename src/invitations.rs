@@ -0,0 +1,186 @@
+use crate::mail::Email;
+use crate::serialise::serialise_data;
+use crate::teams::{insert_team_member, TeamRole};
+use rusqlite::{params, Connection, Error, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+// a link that lets someone register and join a team, or join it with the account they have;
+// times are UTC
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Invitation {
+    pub id: i64,
+    pub token: String,
+    pub team_id: i64,
+    pub team_name: String,
+    pub role: TeamRole,
+    pub created_by: u8,
+    pub expires_at: String,
+    pub max_uses: Option<u32>,
+    pub uses: u32,
+    // only this address can use the link, when set
+    pub email: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct InvitationInput {
+    pub role: TeamRole,
+    pub expires_in_days: u32,
+    pub max_uses: Option<u32>,
+    pub email: Option<String>,
+}
+
+// how long an invitation sent to someone's address stays usable
+pub const EMAILED_INVITATION_DAYS: u32 = 14;
+
+const INVITATION_COLUMNS: &str = "invitation.id, invitation.token, invitation.team_id, team.name,
+    invitation.role, invitation.created_by, invitation.expires_at, invitation.max_uses,
+    invitation.uses, invitation.email";
+
+// unexpired and not used up
+const USABLE: &str = "invitation.expires_at > datetime('now')
+    AND (invitation.max_uses IS NULL OR invitation.uses < invitation.max_uses)";
+
+fn invitation_from_row(row: &Row) -> Result<Invitation, Error> {
+    Ok(Invitation {
+        id: row.get(0)?,
+        token: row.get(1)?,
+        team_id: row.get(2)?,
+        team_name: row.get(3)?,
+        role: row.get(4)?,
+        created_by: row.get(5)?,
+        expires_at: row.get(6)?,
+        max_uses: row.get(7)?,
+        uses: row.get(8)?,
+        email: row.get(9)?,
+    })
+}
+
+// returns the new invitation's token
+pub fn add_invitation(
+    team_id: i64,
+    created_by: u8,
+    invitation: &InvitationInput,
+) -> Result<String, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    insert_invitation(&conn, team_id, created_by, invitation)
+}
+
+// for callers adding several in one transaction
+pub fn insert_invitation(
+    conn: &Connection,
+    team_id: i64,
+    created_by: u8,
+    invitation: &InvitationInput,
+) -> Result<String, Error> {
+    conn.execute(
+        "INSERT INTO invitation (token, team_id, role, created_by, expires_at, max_uses, email)
+            VALUES (lower(hex(randomblob(16))), ?1, ?2, ?3, datetime('now', ?4), ?5, ?6)",
+        params![
+            team_id,
+            invitation.role,
+            created_by,
+            format!("+{} days", invitation.expires_in_days),
+            invitation.max_uses,
+            invitation.email
+        ],
+    )?;
+    conn.query_row(
+        "SELECT token FROM invitation WHERE id = ?1",
+        params![conn.last_insert_rowid()],
+        |row| row.get(0),
+    )
+}
+
+// newest first, including expired and used up ones so admins can see what happened
+pub fn query_invitations_for_team(team_id: i64) -> Result<Vec<Invitation>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM invitation
+            JOIN team ON invitation.team_id = team.id
+            WHERE invitation.team_id = ?1
+            ORDER BY invitation.id DESC",
+        INVITATION_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![team_id], invitation_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// `None` when the token is unknown, expired or used up
+pub fn query_usable_invitation(token: &str) -> Result<Option<Invitation>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM invitation
+                JOIN team ON invitation.team_id = team.id
+                WHERE invitation.token = ?1 AND {}",
+            INVITATION_COLUMNS, USABLE
+        ),
+        params![token],
+        invitation_from_row,
+    )
+    .optional()
+}
+
+// counts one use by `email`; part of the joining transaction so a failed sign-up doesn't use
+// it up
+pub fn redeem_invitation(conn: &Connection, token: &str, email: &str) -> Result<Invitation, Error> {
+    let redeemed = conn.execute(
+        &format!(
+            "UPDATE invitation SET uses = uses + 1 WHERE token = ?1 AND {}
+                AND (invitation.email IS NULL OR invitation.email = ?2 COLLATE NOCASE)",
+            USABLE
+        ),
+        params![token, email],
+    )?;
+    if redeemed == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM invitation
+                JOIN team ON invitation.team_id = team.id
+                WHERE invitation.token = ?1",
+            INVITATION_COLUMNS
+        ),
+        params![token],
+        invitation_from_row,
+    )
+}
+
+// adds an existing account to the invitation's team; fails without using the invitation up
+// when the user is already a member
+pub fn accept_invitation(token: &str, user_id: u8, email: &str) -> Result<Invitation, Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    let invitation = redeem_invitation(&tx, token, email)?;
+    insert_team_member(&tx, invitation.team_id, user_id, invitation.role)?;
+    tx.commit()?;
+    Ok(invitation)
+}
+
+pub fn revoke_invitation(team_id: i64, invitation_id: i64) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "DELETE FROM invitation WHERE team_id = ?1 AND id = ?2",
+        params![team_id, invitation_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// the message for an invitation sent to one address; `link` opens the invitation
+pub fn invitation_email(to: &str, team_name: &str, invited_by: &str, link: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: format!("You're invited to {}", team_name),
+        body: format!(
+            "{} invited you to the workspace '{}'.\n\n\
+             Open this link to join, signing up first if you don't have an account yet:\n{}\n\n\
+             The link works once and expires in {} days.",
+            invited_by, team_name, link, EMAILED_INVITATION_DAYS
+        ),
+    }
+}
@@ -0,0 +1,182 @@
+use crate::db_queries::ProjectStatus;
+use chrono::{Duration, NaiveDate};
+use rocket::form::{FromForm, FromFormField};
+use rocket::http::RawStr;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PER_PAGE: usize = 20;
+pub const MAX_PER_PAGE: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    #[field(value = "asc")]
+    Asc,
+    #[field(value = "desc")]
+    Desc,
+}
+
+impl SortDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        }
+    }
+
+    fn sql(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+}
+
+// the query string of a list page, e.g.
+// `?page=2&per_page=50&sort=name&dir=desc&status=active&from=2024-01-01&to=2024-06-30&owner=3`
+// or `&overdue=true`;
+// pages that don't list projects ignore the filters
+#[derive(FromForm, Debug, Default, Clone)]
+pub struct ListQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    pub sort: Option<String>,
+    pub dir: Option<SortDirection>,
+    pub status: Option<ProjectStatus>,
+    // start dates, both days included
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub owner: Option<u8>,
+    // open projects past their due date
+    pub overdue: Option<bool>,
+}
+
+fn parse_day(date: Option<&str>) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date?, "%Y-%m-%d").ok()
+}
+
+impl ListQuery {
+    pub fn per_page(&self) -> usize {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    // the page asked for, moved back onto the last one when it runs past `total` rows
+    pub fn page(&self, total: usize) -> usize {
+        let page_count = total.div_ceil(self.per_page()).max(1);
+        self.page.unwrap_or(1).clamp(1, page_count)
+    }
+
+    pub fn offset(&self, total: usize) -> usize {
+        (self.page(total) - 1) * self.per_page()
+    }
+
+    // the requested sort if `sorts` allows it, otherwise the first one;
+    // `sorts` pairs the name used in URLs with the SQL it orders by
+    fn sort<'a>(&self, sorts: &[(&'a str, &'a str)]) -> (&'a str, &'a str) {
+        sorts
+            .iter()
+            .find(|(name, _)| Some(*name) == self.sort.as_deref())
+            .copied()
+            .unwrap_or(sorts[0])
+    }
+
+    // `tiebreak` keeps pages stable when the sort column has duplicates
+    pub fn order_by(&self, sorts: &[(&str, &str)], tiebreak: &str) -> String {
+        let direction = self.dir.unwrap_or_default().sql();
+        format!(
+            "ORDER BY {} {}, {} {}",
+            self.sort(sorts).1,
+            direction,
+            tiebreak,
+            direction
+        )
+    }
+
+    pub fn start_from(&self) -> Option<String> {
+        parse_day(self.from.as_deref()).map(|date| date.format("%Y-%m-%d").to_string())
+    }
+
+    // the day after `to`, so the whole of `to` is included
+    pub fn start_until(&self) -> Option<String> {
+        parse_day(self.to.as_deref())
+            .map(|date| (date + Duration::days(1)).format("%Y-%m-%d").to_string())
+    }
+
+    // what the page links in templates need; `extra` are the page's own parameters, like tags
+    pub fn pager(&self, total: usize, sorts: &[(&str, &str)], extra: &[(&str, &str)]) -> Pager {
+        let (sort, _) = self.sort(sorts);
+        let dir = self.dir.unwrap_or_default();
+        let mut filters: Vec<(&str, String)> = Vec::new();
+        if let Some(status) = self.status {
+            filters.push(("status", status.as_str().to_string()));
+        }
+        if let Some(from) = self.start_from() {
+            filters.push(("from", from));
+        }
+        if let Some(to) = parse_day(self.to.as_deref()) {
+            filters.push(("to", to.format("%Y-%m-%d").to_string()));
+        }
+        if let Some(owner) = self.owner {
+            filters.push(("owner", owner.to_string()));
+        }
+        if self.overdue == Some(true) {
+            filters.push(("overdue", "true".to_string()));
+        }
+        if self.per_page.is_some() {
+            filters.push(("per_page", self.per_page().to_string()));
+        }
+        filters.extend(extra.iter().map(|(name, value)| (*name, value.to_string())));
+        let filter_query = query_string(&filters);
+        filters.push(("sort", sort.to_string()));
+        filters.push(("dir", dir.as_str().to_string()));
+
+        Pager {
+            page: self.page(total),
+            per_page: self.per_page(),
+            page_count: total.div_ceil(self.per_page()).max(1),
+            total,
+            sort: sort.to_string(),
+            dir,
+            status: self.status,
+            from: self.start_from().unwrap_or_default(),
+            to: parse_day(self.to.as_deref())
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            owner: self.owner,
+            overdue: self.overdue == Some(true),
+            query: query_string(&filters),
+            filter_query,
+        }
+    }
+}
+
+fn query_string(pairs: &[(&str, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, RawStr::new(value).percent_encode()))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// a page of a list as the `pagination` and `sort_link` macros see it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pager {
+    pub page: usize,
+    pub per_page: usize,
+    pub page_count: usize,
+    pub total: usize,
+    pub sort: String,
+    pub dir: SortDirection,
+    pub status: Option<ProjectStatus>,
+    pub from: String,
+    pub to: String,
+    pub owner: Option<u8>,
+    pub overdue: bool,
+    // every parameter but the page, for page links
+    pub query: String,
+    // the filters alone, for sort links
+    pub filter_query: String,
+}
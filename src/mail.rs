@@ -0,0 +1,104 @@
+use crate::config::{AppConfig, MailBackend};
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum MailError {
+    WriteError(std::io::Error),
+}
+
+impl std::fmt::Display for MailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MailError::WriteError(e) => write!(f, "Error writing mail: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+// drops every message, used when no mail backend is configured
+pub struct NullMailer;
+
+impl Mailer for NullMailer {
+    fn send(&self, _email: &Email) -> Result<(), MailError> {
+        Ok(())
+    }
+}
+
+// writes every message to its own .eml file, handy for local development
+pub struct FileMailer {
+    dir: PathBuf,
+    from: String,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, from: &str) -> Self {
+        FileMailer {
+            dir: dir.into(),
+            from: from.to_string(),
+        }
+    }
+}
+
+// a line break in a header value would start another header, or the body
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], "")
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        fs::create_dir_all(&self.dir).map_err(MailError::WriteError)?;
+
+        let now = Utc::now();
+        let file_name = format!(
+            "{}-{}.eml",
+            now.format("%Y%m%d%H%M%S%f"),
+            email.to.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+        );
+        let message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            header_value(&self.from),
+            header_value(&email.to),
+            header_value(&email.subject),
+            now.to_rfc2822(),
+            email.body
+        );
+
+        fs::write(self.dir.join(file_name), message).map_err(MailError::WriteError)
+    }
+}
+
+pub fn mailer_from_config(config: &AppConfig) -> Box<dyn Mailer> {
+    match config.mail_backend {
+        MailBackend::None => Box::new(NullMailer),
+        MailBackend::File => Box::new(FileMailer::new(&config.mail_dir, &config.mail_from)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_values_cant_add_headers() {
+        assert_eq!(
+            header_value("Due: Launch\r\nBcc: someone@x.io"),
+            "Due: LaunchBcc: someone@x.io"
+        );
+        assert_eq!(header_value("one\ntwo\rthree"), "onetwothree");
+        assert_eq!(header_value("plain"), "plain");
+    }
+}
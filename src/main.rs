@@ -1,27 +1,152 @@
+// handlers return `Result<Redirect, Template>`, and `Template` is large
+#![allow(clippy::result_large_err)]
+
 #[macro_use]
 extern crate rocket;
 
+mod attachments;
 mod bg_func;
+mod board;
+mod calendar;
+mod charts;
+mod comments;
+mod config;
+mod dashboard;
 mod db_queries;
+mod deadlines;
+mod dependencies;
+mod events;
+mod export;
+mod gantt;
+mod htmx;
+mod import;
+mod invitations;
+mod listing;
+mod mail;
+mod markdown;
+mod members;
+mod migrations;
+mod milestones;
+mod negotiate;
+mod notifications;
 mod passwords;
+mod pdf;
+mod reports;
+mod search;
 mod serialise;
+mod stats;
+mod storage;
+mod tags;
+mod tasks;
+mod teams;
+mod time_entries;
 
-use bg_func::print_hello;
+use attachments::{
+    add_attachment, delete_attachment, query_attachment_by_id, query_attachments_for_project,
+    query_storage_keys_for_project, sanitise_file_name, sniff_content_type, storage_key,
+    store_upload, AttachmentInput,
+};
+use bg_func::{print_hello, remind_deadlines};
+use board::{move_card, query_board, query_column_settings, save_column_settings, ColumnSettings};
+use calendar::{
+    month_grid, parse_month, query_calendar_token, query_calendar_token_user,
+    regenerate_calendar_token, render_ics,
+};
+use charts::{bar_chart, ring_chart, weekly_chart};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use comments::{
+    add_comment, count_comment_threads, delete_comment, edit_comment, mentioned_user_ids,
+    query_comment_by_id, query_comment_page, query_comment_threads, THREADS_PER_PAGE,
+};
+use config::{AppConfig, MailBackend, RegistrationMode};
+use dashboard::{
+    add_widget, delete_saved_filter, delete_widget, query_saved_filters, query_widgets,
+    save_filter, saved_query, WidgetKind,
+};
 use db_queries::{
     add_project, add_user, delete_project_by_id, edit_project, query_admin_by_id,
-    query_all_projects, query_all_projects_for_user, query_all_users, query_project_by_id,
-    query_user_by_email, query_user_by_id, Admin, User,
+    query_admin_count, query_all_projects, query_all_projects_for_user, query_all_users,
+    query_project_by_id, query_project_role, query_project_summary, query_projects_page,
+    query_user_by_email, query_user_by_id, query_users_page, Admin, Project, ProjectInput,
+    ProjectPriority, ProjectRole, ProjectStatus, StatusError, User, PROJECT_SORTS, USER_SORTS,
+};
+use dependencies::{
+    add_dependency, query_dependencies, query_dependents, remove_dependency, schedule_warnings,
+};
+use events::subscribe_project_events;
+use export::{export_projects, export_users, ExportFormat};
+use gantt::{render_gantt, Zoom};
+use htmx::HxRequest;
+use import::{
+    import_projects, import_users, preview_projects, preview_users, ImportError, ImportKind,
+    ImportPreview, ImportedInvitation,
+};
+use invitations::{
+    accept_invitation, add_invitation, invitation_email, query_invitations_for_team,
+    query_usable_invitation, revoke_invitation, Invitation, InvitationInput,
+    EMAILED_INVITATION_DAYS,
+};
+use listing::ListQuery;
+use mail::mailer_from_config;
+use markdown::markdown_filter;
+use members::{
+    add_project_member, query_project_members, remove_project_member, transfer_project_ownership,
+    update_project_member_role,
+};
+use migrations::run_migrations;
+use milestones::{
+    add_milestone, delete_milestone, milestone_due_date, query_milestones_for_project,
+    query_milestones_for_team, set_milestone_completed,
+};
+use negotiate::Negotiated;
+use notifications::{
+    count_unread_notifications, enqueue_notification, mark_all_notifications_read,
+    mark_notification_read, query_notification_by_id, query_notification_preference,
+    query_notifications_for_user, save_notification_preference, NotificationPreference,
 };
 use passwords::verify_password;
+use reports::{project_report_pdf, query_project_report, query_summary_report, summary_report_pdf};
+use rocket::data::{Limits, ToByteUnit};
 use rocket::fairing::AdHoc;
 use rocket::form::{Contextual, Form};
-use rocket::fs::{relative, FileServer};
-use rocket::http::{Cookie, CookieJar, Status};
+use rocket::fs::{relative, FileServer, TempFile};
+use rocket::http::uri::Host;
+use rocket::http::{ContentType, Cookie, CookieJar, Header, Status};
+use rocket::outcome::try_outcome;
 use rocket::request::{self, FlashMessage, FromRequest, Outcome, Request};
+use rocket::response::content::RawHtml;
+use rocket::response::stream::{Event, EventStream, TextStream};
 use rocket::response::{Flash, Redirect};
 use rocket::serde::json::Json;
+use rocket::tokio::io::AsyncReadExt;
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::tokio::sync::mpsc::Receiver;
+use rocket::{Shutdown, State};
 use rocket_dyn_templates::{context, Template};
+use search::{search, SearchResult, MAX_RESULTS};
 use serialise::get_flash_msg;
+use stats::{query_completion, query_projects_per_user, query_weekly_activity};
+use std::collections::{HashMap, HashSet};
+use storage::storage_from_config;
+use tags::{
+    add_tag, delete_tag, edit_tag, is_valid_colour, query_project_ids_with_tags,
+    query_tags_by_project, query_tags_for_project, query_tags_for_team, set_project_tags,
+};
+use tasks::{
+    add_task, delete_task, edit_task, move_task, query_task_by_id, query_task_progress,
+    query_task_progress_by_project, query_task_totals, query_tasks_for_project, MoveDirection,
+    TaskInput, TaskStatus,
+};
+use teams::{
+    create_team, is_team_member, query_active_workspace, query_team_members,
+    query_workspaces_for_user, remove_team_member, update_team_member_role, TeamRole, Workspace,
+};
+use time_entries::{
+    add_time_entry, delete_time_entry, duration_filter, entry_times, query_project_time_totals,
+    query_running_timer, query_time_entries_between, start_timer, stop_timer, time_entries_csv,
+    timesheet_rows, week_start,
+};
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for User {
@@ -51,7 +176,12 @@ impl<'r> FromRequest<'r> for Admin {
             if let Ok(user_id) = cookie.value().parse::<u8>() {
                 match query_admin_by_id(user_id) {
                     Ok(admin) => {
-                        if admin.user.admin {
+                        // site admins, and admins of the active workspace
+                        let workspace_admin = match request.guard::<Workspace>().await {
+                            Outcome::Success(workspace) => workspace.is_admin(),
+                            _ => false,
+                        };
+                        if admin.user.admin || workspace_admin {
                             return Outcome::Success(admin);
                         } else {
                             return Outcome::Forward(Status::Unauthorized);
@@ -65,22 +195,102 @@ impl<'r> FromRequest<'r> for Admin {
     }
 }
 
+// the team picked with the workspace switcher, or the user's first team
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Workspace {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Workspace, Self::Error> {
+        let user = try_outcome!(request.guard::<User>().await);
+        let requested = request
+            .cookies()
+            .get_private("workspace_id")
+            .and_then(|cookie| cookie.value().parse::<i64>().ok());
+        match query_active_workspace(user.id, requested) {
+            Ok(workspace) => Outcome::Success(workspace),
+            Err(_) => Outcome::Forward(Status::Unauthorized),
+        }
+    }
+}
+
 #[derive(FromForm, Debug)]
 struct UserRegistrationForm<'v> {
     email: &'v str,
     password: &'v str,
     password1: &'v str,
+    invite: Option<&'v str>,
 }
 
 #[derive(FromForm, Debug)]
 struct AddProjectForm<'v> {
     name: &'v str,
+    description: Option<&'v str>,
+    status: Option<ProjectStatus>,
+    priority: Option<ProjectPriority>,
+    due_date: Option<&'v str>,
+}
+
+impl<'v> AddProjectForm<'v> {
+    // the inline form on /profile only sends a name
+    fn input(&self) -> ProjectInput<'v> {
+        ProjectInput {
+            name: self.name,
+            description: self.description.unwrap_or_default(),
+            status: self.status.unwrap_or(ProjectStatus::Planned),
+            priority: self.priority.unwrap_or(ProjectPriority::Medium),
+            due_date: self.due_date.unwrap_or_default(),
+            end_date: "",
+        }
+    }
 }
 
 #[derive(FromForm, Debug)]
 struct EditProjectForm<'v> {
     name: &'v str,
     end_date: &'v str,
+    description: &'v str,
+    status: ProjectStatus,
+    priority: ProjectPriority,
+    due_date: &'v str,
+    tag_ids: Vec<i64>,
+}
+
+impl<'v> EditProjectForm<'v> {
+    fn input(&self) -> ProjectInput<'v> {
+        ProjectInput {
+            name: self.name,
+            description: self.description,
+            status: self.status,
+            priority: self.priority,
+            due_date: self.due_date,
+            end_date: self.end_date,
+        }
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct TaskForm<'v> {
+    title: &'v str,
+    status: TaskStatus,
+    assignee_id: Option<u8>,
+    due_date: &'v str,
+}
+
+impl<'v> TaskForm<'v> {
+    fn input(&self) -> TaskInput<'v> {
+        TaskInput {
+            title: self.title,
+            status: self.status,
+            assignee_id: self.assignee_id,
+            due_date: self.due_date,
+        }
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct NotificationSettingsForm {
+    deadline_in_app: bool,
+    deadline_email: bool,
 }
 
 #[get("/egg")]
@@ -94,8 +304,9 @@ fn json_route() -> Json<&'static str> {
 }
 
 #[get("/")]
-fn index(user: User) -> Template {
-    Template::render("home", context! {user})
+fn index(user: User, workspace: Workspace) -> Template {
+    let widgets = query_widgets(user.id, workspace.id).unwrap_or_default();
+    Template::render("home", context! {user, workspace, widgets})
 }
 
 #[get("/", rank = 2)]
@@ -103,18 +314,122 @@ fn index_no_auth() -> Template {
     Template::render("home", context! {})
 }
 
-#[get("/profile")]
-fn profile(user: User, flash: Option<FlashMessage<'_>>) -> Template {
+// the tag names asked for, each once, in a stable order
+fn selected_tags(tag: Option<Vec<String>>) -> Vec<String> {
+    let mut tags = tag.unwrap_or_default();
+    tags.retain(|name| !name.is_empty());
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+// the projects carrying every one of `tags`; no tags doesn't narrow anything down
+fn tagged_project_ids(team_id: i64, tags: &[String]) -> Option<Vec<u8>> {
+    if tags.is_empty() {
+        return None;
+    }
+    Some(query_project_ids_with_tags(team_id, tags).unwrap_or_default())
+}
+
+// the selected tags as query parameters, so page and sort links keep them
+fn tag_params(tags: &[String]) -> Vec<(&str, &str)> {
+    tags.iter().map(|name| ("tag", name.as_str())).collect()
+}
+
+#[get("/profile?<tag>&<list..>")]
+fn profile(
+    user: User,
+    workspace: Workspace,
+    tag: Option<Vec<String>>,
+    list: Option<ListQuery>,
+    flash: Option<FlashMessage<'_>>,
+) -> Negotiated {
     let msg = get_flash_msg(flash);
-    match query_all_projects_for_user(user.id) {
-        Ok(projects) => Template::render("profile", context! {projects, user, msg}),
-        Err(_) => Template::render(
-            "error",
-            context! {msg: "Failed to query projects or users."},
+    let list = list.unwrap_or_default();
+    let selected_tags = selected_tags(tag);
+    let tagged = tagged_project_ids(workspace.id, &selected_tags);
+    match query_projects_page(workspace.id, Some(user.id), &list, tagged.as_deref()) {
+        Ok((projects, total)) => {
+            let pager = list.pager(total, PROJECT_SORTS, &tag_params(&selected_tags));
+            let tags = query_tags_for_team(workspace.id).unwrap_or_default();
+            let project_tags = query_tags_by_project(workspace.id).unwrap_or_default();
+            let saved_filters = query_saved_filters(user.id, workspace.id).unwrap_or_default();
+            let widgets = query_widgets(user.id, workspace.id).unwrap_or_default();
+            let widget_kinds: Vec<&str> =
+                WidgetKind::ALL.iter().map(|kind| kind.as_str()).collect();
+            let context = context! {
+                projects, pager, user, workspace, tags, project_tags, selected_tags, saved_filters,
+                widgets, widget_kinds, msg
+            };
+            Negotiated::new("profile", context)
+        }
+        Err(_) => Negotiated::error(
+            Status::InternalServerError,
+            "Failed to query projects or users.",
         ),
     }
 }
 
+#[derive(FromForm, Debug)]
+struct SavedFilterForm<'v> {
+    name: &'v str,
+    // the /profile query string being saved
+    query: &'v str,
+}
+
+#[derive(FromForm, Debug)]
+struct WidgetForm {
+    filter_id: i64,
+    kind: WidgetKind,
+}
+
+#[post("/filters", data = "<form>")]
+fn save_filter_post(
+    user: User,
+    workspace: Workspace,
+    form: Form<SavedFilterForm<'_>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(profile(_, _)));
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Flash::error(redirect, "A saved filter needs a name");
+    }
+    // stored in the same shape /profile links use, whatever was sent
+    let (list, tags) = saved_query(form.query);
+    let query = list.pager(0, PROJECT_SORTS, &tag_params(&tags)).query;
+    match save_filter(user.id, workspace.id, name, &query) {
+        Ok(_) => Flash::success(redirect, format!("Saved filter '{}'", name)),
+        Err(_) => Flash::error(redirect, "Failed to save the filter"),
+    }
+}
+
+#[post("/filters/<id>/delete")]
+fn delete_filter_post(id: i64, user: User) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(profile(_, _)));
+    match delete_saved_filter(user.id, id) {
+        Ok(_) => Flash::success(redirect, "Filter and its widgets removed"),
+        Err(_) => Flash::error(redirect, "Filter not found"),
+    }
+}
+
+#[post("/widgets", data = "<form>")]
+fn add_widget_post(user: User, form: Form<WidgetForm>) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(profile(_, _)));
+    match add_widget(user.id, form.filter_id, form.kind) {
+        Ok(_) => Flash::success(redirect, "Widget pinned to your dashboard"),
+        Err(_) => Flash::error(redirect, "Filter not found"),
+    }
+}
+
+#[post("/widgets/<id>/delete")]
+fn delete_widget_post(id: i64, user: User) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(profile(_, _)));
+    match delete_widget(user.id, id) {
+        Ok(_) => Flash::success(redirect, "Widget removed"),
+        Err(_) => Flash::error(redirect, "Widget not found"),
+    }
+}
+
 #[get("/profile", rank = 2)]
 fn profile_no_auth() -> Flash<Redirect> {
     Flash::success(
@@ -126,7 +441,7 @@ fn profile_no_auth() -> Flash<Redirect> {
 #[get("/login")]
 fn login_get(_user: User) -> Flash<Redirect> {
     Flash::success(
-        Redirect::to(uri!(profile())),
+        Redirect::to(uri!(profile(_, _))),
         "user from /login already logged in; redirecting to /profile",
     )
 }
@@ -180,29 +495,48 @@ fn login_post<'r>(cookies: &CookieJar<'_>, form: Form<Contextual<'r, LoginForm<'
 #[get("/logout")]
 fn logout(cookies: &CookieJar<'_>) -> Redirect {
     cookies.remove_private("user_id_in_cookie");
+    cookies.remove_private("workspace_id");
     Redirect::to(uri!(login_get_no_auth()))
 }
 
+// the projects `user_id` is in that the viewer is in as well; the rest stay hidden from them
+fn shared_projects(
+    user_id: u8,
+    viewer: &User,
+    workspace: &Workspace,
+) -> Result<Vec<Project>, rusqlite::Error> {
+    let visible: HashSet<Option<u8>> = query_all_projects_for_user(viewer.id, workspace.id)?
+        .into_iter()
+        .map(|project| project.id_proj)
+        .collect();
+    let mut projects = query_all_projects_for_user(user_id, workspace.id)?;
+    projects.retain(|project| visible.contains(&project.id_proj));
+    Ok(projects)
+}
+
 #[get("/user/<user_id>")]
-fn user_id(user_id: u8) -> Template {
+fn user_id(user_id: u8, viewer: User, workspace: Workspace) -> Negotiated {
+    if !is_team_member(workspace.id, user_id).unwrap_or(false) {
+        return Negotiated::error(Status::NotFound, "User not found.");
+    }
     match (
         query_user_by_id(user_id),
-        query_all_projects_for_user(user_id),
+        shared_projects(user_id, &viewer, &workspace),
     ) {
         (Ok(user), Ok(projects)) => {
             let context = context! {user, projects};
-            Template::render("user-id", context)
+            Negotiated::new("user-id", context)
         }
-        _ => Template::render(
-            "error",
-            context! {msg: "Failed to query projects or users."},
+        _ => Negotiated::error(
+            Status::InternalServerError,
+            "Failed to query projects or users.",
         ),
     }
 }
 
 #[get("/all-projects-for-user/<id>")]
-fn all_projects_for_user(id: u8) -> Template {
-    match query_all_projects_for_user(id) {
+fn all_projects_for_user(id: u8, viewer: User, workspace: Workspace) -> Template {
+    match shared_projects(id, &viewer, &workspace) {
         Ok(projects) => {
             let serialised_data = projects;
             Template::render("all-projects-for-user", context! {serialised_data})
@@ -211,26 +545,87 @@ fn all_projects_for_user(id: u8) -> Template {
     }
 }
 
-#[get("/project/<id>")]
-fn project_id(id: u8, user: Option<User>) -> Template {
-    match user {
+// fragment for htmx requests on /profile: the changed project row (if any)
+// plus out-of-band updates for the project count and the flash message
+fn project_swap(user: &User, workspace: &Workspace, entry: Option<Project>, msg: &str) -> Template {
+    let project_count = query_all_projects_for_user(user.id, workspace.id)
+        .map(|projects| projects.len())
+        .unwrap_or_default();
+    let project_tags = query_tags_by_project(workspace.id).unwrap_or_default();
+    Template::render(
+        "partials/project-swap",
+        context! {user, entry, project_count, project_tags, msg},
+    )
+}
+
+#[get("/project/<id>?<page>")]
+fn project_id(
+    id: u8,
+    page: Option<usize>,
+    user: Option<User>,
+    workspace: Option<Workspace>,
+    hx: Option<HxRequest>,
+    flash: Option<FlashMessage<'_>>,
+) -> Negotiated {
+    let msg = get_flash_msg(flash);
+    match (user, workspace) {
         // user is logged in
-        Some(user) => match query_project_by_id(id) {
-            // project found
-            Ok(project) => {
-                if user.id == project.user_id {
-                    // user is the owner of the project
-                    Template::render("project-id", context! {user, project})
-                } else {
-                    // user is not the owner of the project
-                    Template::render("profile", context! {user})
+        (Some(user), Some(workspace)) => match project_with_role(&user, &workspace, id) {
+            // user is a member of the project
+            Some((project, _)) if hx.is_some() => {
+                // htmx asks for the row on /profile, e.g. when cancelling an inline edit
+                let project_tags = query_tags_by_project(workspace.id).unwrap_or_default();
+                Negotiated::new(
+                    "partials/project-row",
+                    context! {user, entry: project, project_tags},
+                )
+            }
+            Some((project, role)) => {
+                let progress = query_task_progress(id).unwrap_or_default();
+                let tasks = query_tasks_for_project(id).unwrap_or_default();
+                let members = query_project_members(id).unwrap_or_default();
+                let attachments = query_attachments_for_project(id).unwrap_or_default();
+                let tags = query_tags_for_project(id).unwrap_or_default();
+                let time_totals = query_project_time_totals(id).unwrap_or_default();
+                let time_total: i64 = time_totals.iter().map(|entry| entry.minutes).sum();
+                let running_timer = query_running_timer(user.id).ok().flatten();
+                let milestones = query_milestones_for_project(id).unwrap_or_default();
+                let dependencies = query_dependencies(id).unwrap_or_default();
+                let dependents = query_dependents(id).unwrap_or_default();
+                let mut warnings = schedule_warnings(&project, &dependencies);
+                for dependent in &dependents {
+                    warnings.extend(schedule_warnings(dependent, std::slice::from_ref(&project)));
                 }
+                // anything else in the workspace this user can see could block this project
+                let dependency_options: Vec<Project> =
+                    query_all_projects_for_user(user.id, workspace.id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|candidate| {
+                            candidate.id_proj != Some(id)
+                                && !dependencies
+                                    .iter()
+                                    .any(|dependency| dependency.id_proj == candidate.id_proj)
+                        })
+                        .collect();
+                let thread_count = count_comment_threads(id).unwrap_or_default();
+                let page_count = thread_count.div_ceil(THREADS_PER_PAGE).max(1);
+                let page = page.unwrap_or(1).clamp(1, page_count);
+                let comments = query_comment_threads(id, page).unwrap_or_default();
+                Negotiated::new(
+                    "project-id",
+                    context! {
+                        user, project, role, progress, tasks, members, attachments, tags,
+                        time_totals, time_total, running_timer, milestones, dependencies, dependents,
+                        warnings, dependency_options, comments, thread_count, page, page_count, msg
+                    },
+                )
             }
-            // project not found
-            Err(_) => Template::render("profile", context! {user}),
+            // project not found, or user is not a member of the project
+            None => Negotiated::error(Status::NotFound, "Project not found."),
         },
         // user is not logged in
-        None => Template::render("login", context! {}),
+        _ => Negotiated::new("login", context! {}),
     }
 }
 
@@ -249,31 +644,59 @@ fn add_project_get(user: Option<User>) -> Result<Redirect, Template> {
 fn add_project_post<'r>(
     form: Form<Contextual<'r, AddProjectForm<'r>>>,
     user: Option<User>,
+    workspace: Option<Workspace>,
+    hx: Option<HxRequest>,
 ) -> Result<Redirect, Template> {
-    match user {
-        Some(user) => {
+    match (user, workspace) {
+        (Some(user), Some(workspace)) => {
             let form_data = form.value.as_ref().unwrap();
-            match add_project(form_data.name, user.id) {
-                Ok(id) => Ok(Redirect::to(uri!(project_id(id)))),
+            match add_project(&form_data.input(), user.id, workspace.id) {
+                Ok(id) if hx.is_some() => {
+                    let entry = query_project_by_id(id, workspace.id).ok();
+                    Err(project_swap(&user, &workspace, entry, "Project added"))
+                }
+                Ok(id) => Ok(Redirect::to(uri!(project_id(id, _)))),
+                Err(_) if hx.is_some() => Err(project_swap(
+                    &user,
+                    &workspace,
+                    None,
+                    "Failed to add project.",
+                )),
                 Err(_) => Err(Template::render(
                     "error",
                     context! {msg: "Failed to add project."},
                 )),
             }
         }
-        None => Ok(Redirect::to(uri!("/login"))),
+        _ => Ok(Redirect::to(uri!("/login"))),
     }
 }
 
 #[get("/edit/project/<project_id>")]
-fn edit_project_get(user: Option<User>, project_id: u8) -> Result<Redirect, Template> {
-    match user {
-        Some(user) => {
-            let project = query_project_by_id(project_id).unwrap();
-            let context = context! {user, project};
+fn edit_project_get(
+    user: Option<User>,
+    workspace: Option<Workspace>,
+    project_id: u8,
+    hx: Option<HxRequest>,
+) -> Result<Redirect, Template> {
+    match (user, workspace) {
+        (Some(user), Some(workspace)) => {
+            let Some(project) = editable_project(&user, &workspace, project_id) else {
+                return Err(Template::render(
+                    "error",
+                    context! {msg: "Project not found."},
+                ));
+            };
+            let tags = query_tags_for_team(workspace.id).unwrap_or_default();
+            let project_tag_ids = project_tag_ids(project_id);
+            if hx.is_some() {
+                let context = context! {user, entry: project, tags, project_tag_ids};
+                return Err(Template::render("partials/project-edit-row", context));
+            }
+            let context = context! {user, project, tags, project_tag_ids};
             Err(Template::render("project-edit", context))
         }
-        None => Ok(Redirect::to(uri!("/login"))),
+        _ => Ok(Redirect::to(uri!("/login"))),
     }
 }
 
@@ -281,67 +704,187 @@ fn edit_project_get(user: Option<User>, project_id: u8) -> Result<Redirect, Temp
 fn edit_project_post<'r>(
     form: Form<Contextual<'r, EditProjectForm<'r>>>,
     user: Option<User>,
+    workspace: Option<Workspace>,
     project_id: u8,
+    hx: Option<HxRequest>,
 ) -> Result<Redirect, Template> {
-    match user {
-        Some(user) => {
+    match (user, workspace) {
+        (Some(user), Some(workspace)) => {
             let form_data = form.value.as_ref().unwrap();
-            match edit_project(project_id, form_data.name, form_data.end_date, user) {
-                Ok(updated_project_id) => Ok(Redirect::to(uri!(project_id(updated_project_id)))),
-                Err(_) => Err(Template::render(
-                    "error",
-                    context! {msg: "Failed to edit project."},
-                )),
+            let edited = edit_project(project_id, workspace.id, &form_data.input(), user.clone())
+                .and_then(|id| {
+                    set_project_tags(id, workspace.id, &form_data.tag_ids)?;
+                    Ok(id)
+                });
+            // a full column is worth explaining, anything else is just a failed edit
+            let msg = match &edited {
+                Err(e @ StatusError::WipLimit(..)) => format!("{}.", e),
+                _ => "Failed to edit project.".to_string(),
+            };
+            match edited {
+                Ok(updated_project_id) if hx.is_some() => {
+                    let entry = query_project_by_id(updated_project_id, workspace.id).ok();
+                    Err(project_swap(&user, &workspace, entry, "Project updated"))
+                }
+                Ok(updated_project_id) => Ok(Redirect::to(uri!(project_id(updated_project_id, _)))),
+                Err(_) if hx.is_some() => {
+                    // keep the inline form open and report the problem
+                    let entry = query_project_by_id(project_id, workspace.id).ok();
+                    let tags = query_tags_for_team(workspace.id).unwrap_or_default();
+                    let project_tag_ids = project_tag_ids(project_id);
+                    Err(Template::render(
+                        "partials/project-edit-row",
+                        context! {user, entry, tags, project_tag_ids, msg},
+                    ))
+                }
+                Err(_) => Err(Template::render("error", context! {msg})),
             }
         }
-        None => Ok(Redirect::to(uri!("/login"))),
+        _ => Ok(Redirect::to(uri!("/login"))),
     }
 }
 
+fn project_tag_ids(project_id: u8) -> Vec<i64> {
+    query_tags_for_project(project_id)
+        .unwrap_or_default()
+        .iter()
+        .map(|tag| tag.id)
+        .collect()
+}
+
 #[get("/delete/project/<project_id>")]
-fn delete_project(user: Option<User>, project_id: u8) -> Result<Flash<Redirect>, Template> {
-    match user {
+fn delete_project(
+    user: Option<User>,
+    workspace: Option<Workspace>,
+    project_id: u8,
+    hx: Option<HxRequest>,
+    config: &State<AppConfig>,
+) -> Result<Flash<Redirect>, Template> {
+    match (user, workspace) {
         // user is logged in
-        Some(user) => match delete_project_by_id(project_id, &user) {
-            Ok(_) => Ok(Flash::success(
-                Redirect::to(uri!(profile())),
-                "Project deleted",
-            )),
-            Err(_e) => Err(Template::render(
-                "profile",
-                context! { msg: format!("You can't delete this project, because you're not the owner of this project."), user },
-            )),
-        },
+        (Some(user), Some(workspace)) => {
+            // read before the attachment rows cascade away with the project
+            let stored_files = query_storage_keys_for_project(project_id).unwrap_or_default();
+            let deleted = delete_project_by_id(project_id, workspace.id, &user);
+            if deleted.is_ok() {
+                let storage = storage_from_config(config);
+                for key in &stored_files {
+                    let _ = storage.delete(key);
+                }
+            }
+            match deleted {
+                // an empty row replaces the deleted one
+                Ok(_) if hx.is_some() => {
+                    Err(project_swap(&user, &workspace, None, "Project deleted"))
+                }
+                Err(_) if hx.is_some() => {
+                    let entry = query_project_by_id(project_id, workspace.id).ok();
+                    let msg =
+                    "You can't delete this project, because you're not the owner of this project.";
+                    Err(project_swap(&user, &workspace, entry, msg))
+                }
+                Ok(_) => Ok(Flash::success(
+                    Redirect::to(uri!(profile(_, _))),
+                    "Project deleted",
+                )),
+                Err(_e) => Err(Template::render(
+                    "profile",
+                    context! { msg: format!("You can't delete this project, because you're not the owner of this project."), user },
+                )),
+            }
+        }
         // user is not logged in
-        None => Err(Template::render(
+        _ => Err(Template::render(
             "login",
             context! {msg: "You need to be logged in to delete a project."},
         )),
     }
 }
 
-#[get("/add-user")]
-fn add_user_get(user: Option<User>) -> Result<Redirect, Template> {
-    match user {
-        Some(_user) => Ok(Redirect::to(uri!("/profile"))),
-        None => Err(Template::render("add-user", context! {})),
+// the invitation a sign-up would redeem, or why signing up isn't possible right now
+fn registration_invitation(
+    mode: RegistrationMode,
+    invite: Option<&str>,
+) -> Result<Option<Invitation>, &'static str> {
+    if mode == RegistrationMode::Closed {
+        return Err("Registration is closed.");
+    }
+    match invite {
+        Some(token) => match query_usable_invitation(token) {
+            Ok(Some(invitation)) => Ok(Some(invitation)),
+            _ => Err("This invitation link has expired or has already been used."),
+        },
+        None if mode == RegistrationMode::InviteOnly => {
+            Err("Registration is by invitation only. Ask a workspace admin for a link.")
+        }
+        None => Ok(None),
+    }
+}
+
+#[get("/add-user?<invite>")]
+fn add_user_get(
+    user: Option<User>,
+    invite: Option<&str>,
+    config: &State<AppConfig>,
+) -> Result<Redirect, Template> {
+    match (user, invite) {
+        // someone with an account joins with it instead of signing up again
+        (Some(user), Some(token)) => {
+            let invitation = query_usable_invitation(token).ok().flatten();
+            Err(Template::render("invitation", context! {user, invitation}))
+        }
+        (Some(_user), None) => Ok(Redirect::to(uri!("/profile"))),
+        (None, _) => match registration_invitation(config.registration_mode, invite) {
+            Ok(invitation) => Err(Template::render("add-user", context! {invitation})),
+            Err(blocked) => Err(Template::render("add-user", context! {blocked})),
+        },
     }
 }
 
 #[post("/add-user", data = "<form>")]
-fn add_user_post<'r>(form: Form<Contextual<'r, UserRegistrationForm<'r>>>) -> (Status, Template) {
+fn add_user_post<'r>(
+    form: Form<Contextual<'r, UserRegistrationForm<'r>>>,
+    config: &State<AppConfig>,
+) -> (Status, Template) {
     let template = match form.value {
         Some(ref submission) => {
-            if submission.password == submission.password1 {
-                match add_user(submission.email, submission.password) {
-                    Ok(_) => Template::render("success", context! {}),
-                    Err(e) => Template::render(
+            match registration_invitation(config.registration_mode, submission.invite) {
+                Err(blocked) => Template::render("add-user", context! {blocked}),
+                Ok(Some(invitation))
+                    if invitation
+                        .email
+                        .as_deref()
+                        .is_some_and(|email| !email.eq_ignore_ascii_case(submission.email)) =>
+                {
+                    Template::render(
                         "add-user",
-                        context! {msg: format!("Failed to add user. Error: {}", e)},
-                    ),
+                        context! {invitation, msg: "This invitation is for a different email address."},
+                    )
                 }
-            } else {
-                Template::render("add-user", context! {})
+                Ok(invitation) if submission.password == submission.password1 => {
+                    match add_user(submission.email, submission.password, submission.invite) {
+                        Ok(new_user_id) => {
+                            let _ = match invitation {
+                                Some(invitation) => enqueue_notification(
+                                    new_user_id,
+                                    &format!("Welcome to {}!", invitation.team_name),
+                                    "/profile",
+                                ),
+                                None => enqueue_notification(
+                                    new_user_id,
+                                    "Welcome! Add your first project to get started.",
+                                    "/add-project",
+                                ),
+                            };
+                            Template::render("success", context! {})
+                        }
+                        Err(e) => Template::render(
+                            "add-user",
+                            context! {invitation, msg: format!("Failed to add user. Error: {}", e)},
+                        ),
+                    }
+                }
+                Ok(invitation) => Template::render("add-user", context! {invitation}),
             }
         }
         None => Template::render("add-user", &form.context),
@@ -350,68 +893,2252 @@ fn add_user_post<'r>(form: Form<Contextual<'r, UserRegistrationForm<'r>>>) -> (S
     (form.context.status(), template)
 }
 
-#[get("/all-users")]
-fn all_users(user: User, admin: Admin) -> Template {
-    match query_all_users() {
-        Ok(all_users) => {
-            let user_count = all_users.len();
-            let admin_count = all_users.iter().filter(|user| user.admin).count();
-            let context = context! {all_users, user, admin, user_count, admin_count};
-            Template::render("all-users", context)
+#[derive(FromForm, Debug)]
+struct AcceptInvitationForm<'v> {
+    token: &'v str,
+}
+
+#[post("/invitations/accept", data = "<form>")]
+fn accept_invitation_post(
+    user: User,
+    cookies: &CookieJar<'_>,
+    form: Form<AcceptInvitationForm<'_>>,
+) -> Flash<Redirect> {
+    match accept_invitation(form.token, user.id, &user.email) {
+        Ok(invitation) => {
+            cookies.add_private(Cookie::new(
+                "workspace_id",
+                invitation.team_id.to_string(),
+            ));
+            Flash::success(
+                Redirect::to(uri!(workspaces_get())),
+                format!("Joined {}", invitation.team_name),
+            )
+        }
+        Err(_) => Flash::error(
+            Redirect::to(uri!(profile(_, _))),
+            "This invitation can't be used: it has expired, was meant for someone else, or you're already in that workspace",
+        ),
+    }
+}
+
+#[get("/all-users?<list..>")]
+fn all_users(
+    user: User,
+    admin: Admin,
+    workspace: Workspace,
+    list: Option<ListQuery>,
+) -> Negotiated {
+    let list = list.unwrap_or_default();
+    match query_users_page(workspace.id, &list) {
+        Ok((all_users, user_count)) => {
+            let pager = list.pager(user_count, USER_SORTS, &[]);
+            let admin_count = query_admin_count(workspace.id).unwrap_or_default();
+            let context =
+                context! {all_users, pager, user, admin, workspace, user_count, admin_count};
+            Negotiated::new("all-users", context)
         }
-        Err(_) => Template::render("error", context! {msg: "Failed to query users."}),
+        Err(_) => Negotiated::error(Status::InternalServerError, "Failed to query users."),
     }
 }
 
-#[get("/all-projects")]
-fn all_projects(user: User, admin: Admin) -> Template {
-    match (query_all_projects(), query_all_users()) {
-        (Ok(all_projects), Ok(all_users)) => {
-            let no_end_date = all_projects
+#[get("/all-projects?<tag>&<list..>")]
+fn all_projects(
+    user: User,
+    admin: Admin,
+    workspace: Workspace,
+    tag: Option<Vec<String>>,
+    list: Option<ListQuery>,
+) -> Negotiated {
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let list = list.unwrap_or_default();
+    let selected_tags = selected_tags(tag);
+    let tagged = tagged_project_ids(workspace.id, &selected_tags);
+    match (
+        query_projects_page(workspace.id, None, &list, tagged.as_deref()),
+        query_project_summary(workspace.id, &list, tagged.as_deref(), &now),
+        query_all_users(workspace.id),
+        query_task_progress_by_project(workspace.id),
+        query_task_totals(workspace.id, &list, tagged.as_deref()),
+    ) {
+        (
+            Ok((all_projects, project_count)),
+            Ok((counts, overdue_count)),
+            Ok(all_users),
+            Ok(mut progress),
+            Ok(task_totals),
+        ) => {
+            let pager = list.pager(project_count, PROJECT_SORTS, &tag_params(&selected_tags));
+            let tags = query_tags_for_team(workspace.id).unwrap_or_default();
+            let project_tags = query_tags_by_project(workspace.id).unwrap_or_default();
+            let status_counts: Vec<_> = ProjectStatus::ALL
+                .iter()
+                .map(|status| {
+                    let count = counts
+                        .iter()
+                        .find(|(counted, _)| counted == status)
+                        .map(|(_, count)| *count)
+                        .unwrap_or_default();
+                    let percentage = if project_count > 0 {
+                        (count as f64 / project_count as f64) * 100.0
+                    } else {
+                        0.0
+                    };
+                    context! {status: status.as_str(), count, percentage}
+                })
+                .collect();
+
+            // only the listed projects get an entry, each of them even without tasks, so the
+            // template can index freely
+            let task_progress: HashMap<String, _> = all_projects
                 .iter()
-                .filter(|project| project.end_date.is_empty())
-                .count();
-            let project_count = all_projects.len();
-            let percentage = if project_count > 0 {
-                (no_end_date as f64 / project_count as f64) * 100.0
+                .filter_map(|project| project.id_proj)
+                .map(|id| {
+                    let key = id.to_string();
+                    let entry = progress.remove(&key).unwrap_or_default();
+                    (key, entry)
+                })
+                .collect();
+            let task_total = task_totals.total;
+            let task_done = task_totals.done;
+            let task_percentage = task_totals.percentage;
+
+            let context = context! {
+                all_projects, all_users, user, admin, workspace, project_count, status_counts, overdue_count,
+                task_progress, task_total, task_done, task_percentage, tags, project_tags, selected_tags, pager
+            };
+            Negotiated::new("all-projects", context)
+        }
+        _ => Negotiated::error(
+            Status::InternalServerError,
+            "Failed to query projects or users.",
+        ),
+    }
+}
+
+// a file streamed to the client while it's still being read from the database
+#[derive(Responder)]
+struct Download<R> {
+    inner: (ContentType, R),
+    disposition: Header<'static>,
+}
+
+fn download(
+    format: ExportFormat,
+    name: &str,
+    mut chunks: Receiver<String>,
+) -> Download<TextStream![String]> {
+    Download {
+        inner: (
+            format.content_type(),
+            TextStream! {
+                while let Some(chunk) = chunks.recv().await {
+                    yield chunk;
+                }
+            },
+        ),
+        disposition: Header::new(
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}-{}.{}\"",
+                name,
+                Local::now().format("%Y-%m-%d"),
+                format.as_str()
+            ),
+        ),
+    }
+}
+
+// the projects on /profile with the same filters and order, all pages at once
+#[get("/profile/export?<format>&<tag>&<list..>")]
+fn export_own_projects(
+    user: User,
+    workspace: Workspace,
+    format: Option<ExportFormat>,
+    tag: Option<Vec<String>>,
+    list: Option<ListQuery>,
+) -> Download<TextStream![String]> {
+    let format = format.unwrap_or_default();
+    let tagged = tagged_project_ids(workspace.id, &selected_tags(tag));
+    let chunks = export_projects(
+        workspace.id,
+        Some(user.id),
+        list.unwrap_or_default(),
+        tagged,
+        format,
+    );
+    download(format, "my-projects", chunks)
+}
+
+// the projects on /all-projects with the same filters and order, all pages at once
+#[get("/all-projects/export?<format>&<tag>&<list..>")]
+fn export_all_projects(
+    _admin: Admin,
+    workspace: Workspace,
+    format: Option<ExportFormat>,
+    tag: Option<Vec<String>>,
+    list: Option<ListQuery>,
+) -> Download<TextStream![String]> {
+    let format = format.unwrap_or_default();
+    let tagged = tagged_project_ids(workspace.id, &selected_tags(tag));
+    let chunks = export_projects(workspace.id, None, list.unwrap_or_default(), tagged, format);
+    download(format, "projects", chunks)
+}
+
+#[get("/all-users/export?<format>&<list..>")]
+fn export_all_users(
+    _admin: Admin,
+    workspace: Workspace,
+    format: Option<ExportFormat>,
+    list: Option<ListQuery>,
+) -> Download<TextStream![String]> {
+    let format = format.unwrap_or_default();
+    let chunks = export_users(workspace.id, list.unwrap_or_default(), format);
+    download(format, "users", chunks)
+}
+
+#[get("/import")]
+fn import_get(user: User, admin: Admin, workspace: Workspace) -> Template {
+    let kinds = ImportKind::ALL.map(|kind| kind.as_str());
+    Template::render("import", context! {user, admin, workspace, kinds})
+}
+
+#[derive(FromForm, Debug)]
+struct ImportForm<'r> {
+    kind: ImportKind,
+    file: TempFile<'r>,
+    // check the file and show what would happen, without saving anything
+    dry_run: bool,
+    // email the invitation links, instead of showing them here
+    send_invitations: bool,
+}
+
+// what an import did, or why it didn't
+enum ImportOutcome {
+    Preview(ImportPreview),
+    Projects(ImportPreview, usize),
+    Users(ImportPreview, Vec<ImportedInvitation>),
+}
+
+async fn run_import(
+    form: &ImportForm<'_>,
+    user: &User,
+    workspace: &Workspace,
+) -> Result<ImportOutcome, ImportError> {
+    let mut bytes = Vec::new();
+    let mut reader = form.file.open().await.map_err(|_| ImportError::NotText)?;
+    reader
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|_| ImportError::NotText)?;
+    let csv = String::from_utf8(bytes).map_err(|_| ImportError::NotText)?;
+
+    // the same checks run again before saving, and nothing is saved if any row fails them
+    let team_id = workspace.id;
+    let outcome = match form.kind {
+        ImportKind::Projects => {
+            let (preview, projects) = preview_projects(team_id, user.id, &csv)?;
+            if form.dry_run || !preview.is_valid() {
+                ImportOutcome::Preview(preview)
+            } else {
+                let ids = import_projects(team_id, &projects)?;
+                ImportOutcome::Projects(preview, ids.len())
+            }
+        }
+        ImportKind::Users => {
+            let (preview, users) = preview_users(team_id, &csv)?;
+            if form.dry_run || !preview.is_valid() {
+                ImportOutcome::Preview(preview)
+            } else {
+                let invitations = import_users(team_id, user.id, &users)?;
+                // people who already have an account hear about it here as well
+                for invitation in &invitations {
+                    if let Ok(invitee) = query_user_by_email(invitation.email.clone()) {
+                        let _ = enqueue_notification(
+                            invitee.id,
+                            &format!(
+                                "{} invited you to the workspace '{}'",
+                                user.email, workspace.name
+                            ),
+                            &format!("/add-user?invite={}", invitation.token),
+                        );
+                    }
+                }
+                ImportOutcome::Users(preview, invitations)
+            }
+        }
+    };
+    Ok(outcome)
+}
+
+#[post("/import", data = "<form>")]
+async fn import_post(
+    user: User,
+    admin: Admin,
+    workspace: Workspace,
+    host: &Host<'_>,
+    config: &State<AppConfig>,
+    form: Form<Contextual<'_, ImportForm<'_>>>,
+) -> Template {
+    let kinds = ImportKind::ALL.map(|kind| kind.as_str());
+    let Some(ref form_data) = form.value else {
+        let msg = format!(
+            "Choose a CSV file of at most {} to import",
+            config.max_upload_bytes.bytes()
+        );
+        return Template::render("import", context! {user, admin, workspace, kinds, msg});
+    };
+    let dry_run = form_data.dry_run;
+    let kind = form_data.kind;
+    match run_import(form_data, &user, &workspace).await {
+        Ok(ImportOutcome::Preview(preview)) => {
+            let msg = if preview.is_valid() {
+                format!(
+                    "Dry run: all {} rows can be imported, upload the file again without dry run to import them",
+                    preview.rows.len()
+                )
+            } else if dry_run {
+                format!("Dry run: {} rows need fixing", preview.error_count)
+            } else {
+                format!(
+                    "Nothing was imported, {} rows need fixing first",
+                    preview.error_count
+                )
+            };
+            Template::render(
+                "import",
+                context! {user, admin, workspace, kinds, kind, preview, dry_run, msg},
+            )
+        }
+        Ok(ImportOutcome::Projects(preview, count)) => {
+            let msg = format!("{} projects imported", count);
+            Template::render(
+                "import",
+                context! {user, admin, workspace, kinds, kind, preview, msg},
+            )
+        }
+        Ok(ImportOutcome::Users(preview, invitations)) => {
+            let mut msg = format!("{} people invited to {}", invitations.len(), workspace.name);
+            if config.registration_mode == RegistrationMode::Closed {
+                msg.push_str(", but registration is closed, so only those who already have an account can join");
+            }
+            let host = host.to_string();
+            let link = |invitation: &ImportedInvitation| {
+                config.absolute_url(&host, &format!("/add-user?invite={}", invitation.token))
+            };
+            // links that weren't mailed are listed so they can be passed on
+            let send = form_data.send_invitations && config.mail_backend != MailBackend::None;
+            if form_data.send_invitations && !send {
+                msg.push_str("; mail isn't set up, so pass on the links below");
+            }
+            let unsent: Vec<&ImportedInvitation> = if send {
+                let mailer = mailer_from_config(config);
+                let failed: Vec<&ImportedInvitation> = invitations
+                    .iter()
+                    .filter(|invitation| {
+                        let email = invitation_email(
+                            &invitation.email,
+                            &workspace.name,
+                            &user.email,
+                            &link(invitation),
+                        );
+                        mailer.send(&email).is_err()
+                    })
+                    .collect();
+                if !failed.is_empty() {
+                    msg.push_str(&format!(
+                        "; {} invitations couldn't be mailed, pass on their links below",
+                        failed.len()
+                    ));
+                }
+                failed
             } else {
-                0.0
+                invitations.iter().collect()
             };
+            let links: Vec<_> = unsent
+                .into_iter()
+                .map(|invitation| context! {email: &invitation.email, link: link(invitation)})
+                .collect();
+            Template::render(
+                "import",
+                context! {user, admin, workspace, kinds, kind, preview, links, msg},
+            )
+        }
+        Err(e) => Template::render(
+            "import",
+            context! {user, admin, workspace, kinds, kind, dry_run, msg: e.to_string()},
+        ),
+    }
+}
+
+// how many weeks of activity the analytics page charts
+const ANALYTICS_WEEKS: i64 = 12;
 
-            let context = context! {all_projects, all_users, user, admin, no_end_date, project_count, percentage};
-            Template::render("all-projects", context)
+#[get("/analytics")]
+fn analytics(user: User, admin: Admin, workspace: Workspace) -> Negotiated {
+    let today = Local::now().date_naive();
+    match (
+        query_projects_per_user(workspace.id),
+        query_completion(workspace.id),
+        query_weekly_activity(workspace.id, ANALYTICS_WEEKS, today),
+    ) {
+        (Ok(per_user), Ok(completion), Ok(weekly)) => {
+            let owners: Vec<(String, usize)> = per_user
+                .iter()
+                .map(|entry| (entry.email.clone(), entry.total))
+                .collect();
+            let per_user_svg = bar_chart(&owners, "#1e88e5", "projects per member");
+            let completion_svg = ring_chart(completion.rate, "completion rate");
+            let weekly_svg = weekly_chart(&weekly);
+            let context = context! {
+                user, admin, workspace, per_user, completion, weekly, per_user_svg, completion_svg,
+                weekly_svg, weeks: ANALYTICS_WEEKS
+            };
+            Negotiated::new("analytics", context)
         }
-        _ => Template::render(
-            "error",
-            context! {msg: "Failed to query projects or users."},
+        _ => Negotiated::error(Status::InternalServerError, "Failed to compute statistics."),
+    }
+}
+
+// the project in the active workspace and this user's role in it, if they are a member
+fn project_with_role(
+    user: &User,
+    workspace: &Workspace,
+    project_id: u8,
+) -> Option<(Project, ProjectRole)> {
+    let role = query_project_role(user.id, project_id).ok().flatten()?;
+    let project = query_project_by_id(project_id, workspace.id).ok()?;
+    Some((project, role))
+}
+
+// the project, if this user may change it
+fn editable_project(user: &User, workspace: &Workspace, project_id: u8) -> Option<Project> {
+    project_with_role(user, workspace, project_id)
+        .filter(|(_, role)| role.can_edit())
+        .map(|(project, _)| project)
+}
+
+// tasks can only go to people in the workspace, who are the ones the form offers
+fn valid_assignee(workspace: &Workspace, task: &TaskInput) -> bool {
+    task.assignee_id
+        .is_none_or(|id| is_team_member(workspace.id, id).unwrap_or(false))
+}
+
+fn notify_assignee(user: &User, project: &Project, task: &TaskInput) {
+    if let Some(assignee_id) = task.assignee_id.filter(|id| *id != user.id) {
+        let _ = enqueue_notification(
+            assignee_id,
+            &format!(
+                "{} assigned you '{}' in project '{}'",
+                user.email, task.title, project.name
+            ),
+            &format!("/project/{}/tasks", project.id_proj.unwrap_or_default()),
+        );
+    }
+}
+
+#[get("/project/<id>/tasks")]
+fn project_tasks(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    flash: Option<FlashMessage<'_>>,
+) -> Template {
+    let msg = get_flash_msg(flash);
+    let Some((project, role)) = project_with_role(&user, &workspace, id) else {
+        return Template::render("error", context! {msg: "Project not found."});
+    };
+    match (
+        query_tasks_for_project(id),
+        query_task_progress(id),
+        query_all_users(workspace.id),
+    ) {
+        (Ok(tasks), Ok(progress), Ok(all_users)) => Template::render(
+            "project-tasks",
+            context! {user, project, role, tasks, progress, all_users, msg},
         ),
+        _ => Template::render("error", context! {msg: "Failed to query tasks."}),
     }
 }
 
-#[catch(404)]
-fn not_found() -> Template {
-    Template::render("catchers/404", context! {})
+#[post("/project/<id>/tasks", data = "<form>")]
+fn add_task_post<'r>(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<Contextual<'r, TaskForm<'r>>>,
+) -> Result<Flash<Redirect>, Template> {
+    let Some(project) = editable_project(&user, &workspace, id) else {
+        return Err(Template::render(
+            "error",
+            context! {msg: "Project not found."},
+        ));
+    };
+    let Some(ref form_data) = form.value else {
+        return Ok(Flash::error(
+            Redirect::to(uri!(project_tasks(id))),
+            "A task needs a title and a status",
+        ));
+    };
+    let task = form_data.input();
+    if !valid_assignee(&workspace, &task) {
+        return Ok(Flash::error(
+            Redirect::to(uri!(project_tasks(id))),
+            "The assignee isn't in this workspace",
+        ));
+    }
+    match add_task(id, &task) {
+        Ok(_) => {
+            notify_assignee(&user, &project, &task);
+            Ok(Flash::success(
+                Redirect::to(uri!(project_tasks(id))),
+                "Task added",
+            ))
+        }
+        Err(_) => Err(Template::render(
+            "error",
+            context! {msg: "Failed to add task."},
+        )),
+    }
 }
 
-#[catch(500)]
-fn server_error() -> Template {
-    Template::render("catchers/500", context! {})
+#[get("/project/<id>/tasks/<task_id>")]
+fn edit_task_get(
+    id: u8,
+    task_id: i64,
+    user: User,
+    workspace: Workspace,
+    flash: Option<FlashMessage<'_>>,
+) -> Template {
+    let msg = get_flash_msg(flash);
+    let Some(project) = editable_project(&user, &workspace, id) else {
+        return Template::render("error", context! {msg: "Project not found."});
+    };
+    match (query_task_by_id(id, task_id), query_all_users(workspace.id)) {
+        (Ok(task), Ok(all_users)) => {
+            Template::render("task-edit", context! {user, project, task, all_users, msg})
+        }
+        _ => Template::render("error", context! {msg: "Task not found."}),
+    }
 }
 
-#[launch]
-fn rocket() -> _ {
-    rocket::build()
-        .mount(
-            "/",
-            routes![
-                egg,
-                json_route,
-                index,
-                index_no_auth,
-                profile,
-                profile_no_auth,
-                login_get,
-                login_get_no_auth,
+#[post("/project/<id>/tasks/<task_id>", data = "<form>")]
+fn edit_task_post<'r>(
+    id: u8,
+    task_id: i64,
+    user: User,
+    workspace: Workspace,
+    form: Form<Contextual<'r, TaskForm<'r>>>,
+) -> Result<Flash<Redirect>, Template> {
+    let Some(project) = editable_project(&user, &workspace, id) else {
+        return Err(Template::render(
+            "error",
+            context! {msg: "Project not found."},
+        ));
+    };
+    let Some(ref form_data) = form.value else {
+        return Ok(Flash::error(
+            Redirect::to(uri!(edit_task_get(id, task_id))),
+            "A task needs a title and a status",
+        ));
+    };
+    let task = form_data.input();
+    if !valid_assignee(&workspace, &task) {
+        return Ok(Flash::error(
+            Redirect::to(uri!(edit_task_get(id, task_id))),
+            "The assignee isn't in this workspace",
+        ));
+    }
+    let previous_assignee = query_task_by_id(id, task_id)
+        .ok()
+        .and_then(|task| task.assignee_id);
+    match edit_task(id, task_id, &task) {
+        Ok(_) => {
+            if task.assignee_id != previous_assignee {
+                notify_assignee(&user, &project, &task);
+            }
+            Ok(Flash::success(
+                Redirect::to(uri!(project_tasks(id))),
+                "Task updated",
+            ))
+        }
+        Err(_) => Err(Template::render(
+            "error",
+            context! {msg: "Failed to edit task."},
+        )),
+    }
+}
+
+#[post("/project/<id>/tasks/<task_id>/delete")]
+fn delete_task_post(
+    id: u8,
+    task_id: i64,
+    user: User,
+    workspace: Workspace,
+) -> Result<Flash<Redirect>, Template> {
+    if editable_project(&user, &workspace, id).is_none() {
+        return Err(Template::render(
+            "error",
+            context! {msg: "Project not found."},
+        ));
+    }
+    match delete_task(id, task_id) {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(uri!(project_tasks(id))),
+            "Task deleted",
+        )),
+        Err(_) => Err(Template::render(
+            "error",
+            context! {msg: "Failed to delete task."},
+        )),
+    }
+}
+
+#[post("/project/<id>/tasks/<task_id>/move?<direction>")]
+fn move_task_post(
+    id: u8,
+    task_id: i64,
+    direction: MoveDirection,
+    user: User,
+    workspace: Workspace,
+) -> Result<Redirect, Template> {
+    if editable_project(&user, &workspace, id).is_none() {
+        return Err(Template::render(
+            "error",
+            context! {msg: "Project not found."},
+        ));
+    }
+    match move_task(id, task_id, direction) {
+        Ok(_) => Ok(Redirect::to(uri!(project_tasks(id)))),
+        Err(_) => Err(Template::render(
+            "error",
+            context! {msg: "Failed to move task."},
+        )),
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct CommentForm<'v> {
+    #[field(validate = len(1..))]
+    body: &'v str,
+    parent_id: Option<i64>,
+}
+
+// where a comment shows up: its thread's page on the project, scrolled to the comment
+fn comment_link(project_id: u8, comment_id: i64) -> String {
+    let page = query_comment_page(project_id, comment_id).unwrap_or(1);
+    format!(
+        "/project/{}?page={}#comment-{}",
+        project_id, page, comment_id
+    )
+}
+
+// project members mentioned in `body`, except the author and anyone in `already_notified`
+fn notify_mentions(
+    user: &User,
+    project: &Project,
+    comment_id: i64,
+    body: &str,
+    already_notified: &[u8],
+) {
+    let project_id = project.id_proj.unwrap_or_default();
+    let members = query_project_members(project_id).unwrap_or_default();
+    let link = comment_link(project_id, comment_id);
+    for member_id in mentioned_user_ids(body, &members) {
+        if member_id != user.id && !already_notified.contains(&member_id) {
+            let _ = enqueue_notification(
+                member_id,
+                &format!("{} mentioned you in '{}'", user.email, project.name),
+                &link,
+            );
+        }
+    }
+}
+
+// any member can take part in the discussion, viewers included
+#[post("/project/<id>/comments", data = "<form>")]
+fn add_comment_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<Contextual<'_, CommentForm<'_>>>,
+) -> Result<Redirect, Flash<Redirect>> {
+    let Some((project, _)) = project_with_role(&user, &workspace, id) else {
+        return Err(Flash::error(
+            Redirect::to(uri!(profile(_, _))),
+            "Project not found",
+        ));
+    };
+    let Some(ref form_data) = form.value else {
+        return Err(Flash::error(
+            Redirect::to(uri!(project_id(id, _))),
+            "A comment can't be empty",
+        ));
+    };
+    match add_comment(id, user.id, form_data.parent_id, form_data.body) {
+        Ok(comment_id) => {
+            notify_mentions(&user, &project, comment_id, form_data.body, &[]);
+            Ok(Redirect::to(comment_link(id, comment_id)))
+        }
+        Err(_) => Err(Flash::error(
+            Redirect::to(uri!(project_id(id, _))),
+            "Failed to add comment",
+        )),
+    }
+}
+
+#[get("/project/<id>/comments/<comment_id>/edit")]
+fn edit_comment_get(id: u8, comment_id: i64, user: User, workspace: Workspace) -> Template {
+    let Some((project, _)) = project_with_role(&user, &workspace, id) else {
+        return Template::render("error", context! {msg: "Project not found."});
+    };
+    match query_comment_by_id(id, comment_id) {
+        Ok(comment) if comment.user_id == user.id && !comment.deleted => {
+            Template::render("comment-edit", context! {user, project, comment})
+        }
+        _ => Template::render(
+            "error",
+            context! {msg: "You can only edit your own comments."},
+        ),
+    }
+}
+
+#[post("/project/<id>/comments/<comment_id>", data = "<form>")]
+fn edit_comment_post(
+    id: u8,
+    comment_id: i64,
+    user: User,
+    workspace: Workspace,
+    form: Form<Contextual<'_, CommentForm<'_>>>,
+) -> Result<Redirect, Flash<Redirect>> {
+    let Some((project, _)) = project_with_role(&user, &workspace, id) else {
+        return Err(Flash::error(
+            Redirect::to(uri!(profile(_, _))),
+            "Project not found",
+        ));
+    };
+    let Some(ref form_data) = form.value else {
+        return Err(Flash::error(
+            Redirect::to(uri!(edit_comment_get(id, comment_id))),
+            "A comment can't be empty",
+        ));
+    };
+    // people mentioned before the edit have already been told
+    let members = query_project_members(id).unwrap_or_default();
+    let previous_mentions = query_comment_by_id(id, comment_id)
+        .map(|comment| mentioned_user_ids(&comment.body, &members))
+        .unwrap_or_default();
+    match edit_comment(id, comment_id, user.id, form_data.body) {
+        Ok(_) => {
+            notify_mentions(
+                &user,
+                &project,
+                comment_id,
+                form_data.body,
+                &previous_mentions,
+            );
+            Ok(Redirect::to(comment_link(id, comment_id)))
+        }
+        Err(_) => Err(Flash::error(
+            Redirect::to(uri!(project_id(id, _))),
+            "You can only edit your own comments",
+        )),
+    }
+}
+
+#[post("/project/<id>/comments/<comment_id>/delete")]
+fn delete_comment_post(
+    id: u8,
+    comment_id: i64,
+    user: User,
+    workspace: Workspace,
+) -> Flash<Redirect> {
+    if project_with_role(&user, &workspace, id).is_none() {
+        return Flash::error(Redirect::to(uri!(profile(_, _))), "Project not found");
+    }
+    let page = query_comment_page(id, comment_id).ok();
+    let redirect = Redirect::to(uri!(project_id(id, page)));
+    match delete_comment(id, comment_id, user.id) {
+        Ok(_) => Flash::success(redirect, "Comment deleted"),
+        Err(_) => Flash::error(redirect, "You can only delete your own comments"),
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct AttachmentForm<'r> {
+    file: TempFile<'r>,
+}
+
+// file contents with headers that keep browsers from guessing a different type
+#[derive(Responder)]
+struct AttachmentFile {
+    inner: (ContentType, Vec<u8>),
+    disposition: Header<'static>,
+    nosniff: Header<'static>,
+}
+
+#[post("/project/<id>/attachments", data = "<form>")]
+async fn upload_attachment_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    config: &State<AppConfig>,
+    form: Form<Contextual<'_, AttachmentForm<'_>>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(project_id(id, _)));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can attach files");
+    }
+    let Some(ref form_data) = form.value else {
+        return Flash::error(
+            redirect,
+            format!(
+                "Choose a file of at most {} to upload",
+                config.max_upload_bytes.bytes()
+            ),
+        );
+    };
+    let file = &form_data.file;
+    if file.len() == 0 || file.len() > config.max_upload_bytes {
+        return Flash::error(
+            redirect,
+            format!(
+                "Choose a file of at most {} to upload",
+                config.max_upload_bytes.bytes()
+            ),
+        );
+    }
+
+    let mut bytes = Vec::new();
+    let read = match file.open().await {
+        Ok(mut reader) => reader.read_to_end(&mut bytes).await,
+        Err(e) => Err(e),
+    };
+    if read.is_err() {
+        return Flash::error(redirect, "Failed to read the upload");
+    }
+
+    let declared = file
+        .content_type()
+        .map(|content_type| format!("{}/{}", content_type.top(), content_type.sub()))
+        .unwrap_or_default();
+    let content_type = sniff_content_type(&bytes, &declared);
+    if !config
+        .upload_content_types
+        .iter()
+        .any(|allowed| allowed == content_type)
+    {
+        return Flash::error(
+            redirect,
+            format!("Files of type {} aren't allowed", content_type),
+        );
+    }
+
+    // browsers may send a full path; only the last part is the name
+    let file_name = file
+        .raw_name()
+        .map(|name| name.dangerous_unsafe_unsanitized_raw().as_str())
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .map(sanitise_file_name)
+        .unwrap_or_else(|| sanitise_file_name(""));
+    let key = storage_key(id, &file_name);
+    let size = bytes.len() as u64;
+
+    // writing files and scaling images would otherwise hold up the async workers
+    let storage = storage_from_config(config);
+    let stored_key = key.clone();
+    let is_image = content_type.starts_with("image/");
+    let stored = rocket::tokio::task::spawn_blocking(move || {
+        store_upload(storage.as_ref(), &stored_key, &bytes, is_image)
+    })
+    .await;
+    let Ok(Ok(thumbnail_key)) = stored else {
+        return Flash::error(redirect, "Failed to store the upload");
+    };
+
+    let attachment = AttachmentInput {
+        file_name: &file_name,
+        content_type,
+        size,
+        storage_key: &key,
+        thumbnail_key: thumbnail_key.as_deref(),
+    };
+    match add_attachment(id, user.id, &attachment) {
+        Ok(_) => Flash::success(redirect, format!("{} attached", file_name)),
+        Err(_) => {
+            let storage = storage_from_config(config);
+            let _ = storage.delete(&key);
+            if let Some(thumbnail_key) = thumbnail_key {
+                let _ = storage.delete(&thumbnail_key);
+            }
+            Flash::error(redirect, "Failed to save the attachment")
+        }
+    }
+}
+
+// images open in the browser, everything else downloads
+#[get("/project/<id>/attachments/<attachment_id>")]
+fn download_attachment(
+    id: u8,
+    attachment_id: i64,
+    user: User,
+    workspace: Workspace,
+    config: &State<AppConfig>,
+) -> Option<AttachmentFile> {
+    project_with_role(&user, &workspace, id)?;
+    let attachment = query_attachment_by_id(id, attachment_id).ok()?;
+    let bytes = storage_from_config(config)
+        .get(&attachment.storage_key)
+        .ok()?;
+    let content_type =
+        ContentType::parse_flexible(&attachment.content_type).unwrap_or(ContentType::Binary);
+    let disposition = if attachment.content_type.starts_with("image/") {
+        "inline"
+    } else {
+        "attachment"
+    };
+    Some(AttachmentFile {
+        inner: (content_type, bytes),
+        disposition: Header::new(
+            "Content-Disposition",
+            format!("{}; filename=\"{}\"", disposition, attachment.file_name),
+        ),
+        nosniff: Header::new("X-Content-Type-Options", "nosniff"),
+    })
+}
+
+#[get("/project/<id>/attachments/<attachment_id>/thumbnail")]
+fn attachment_thumbnail(
+    id: u8,
+    attachment_id: i64,
+    user: User,
+    workspace: Workspace,
+    config: &State<AppConfig>,
+) -> Option<(ContentType, Vec<u8>)> {
+    project_with_role(&user, &workspace, id)?;
+    let attachment = query_attachment_by_id(id, attachment_id).ok()?;
+    let bytes = storage_from_config(config)
+        .get(attachment.thumbnail_key.as_deref()?)
+        .ok()?;
+    Some((ContentType::PNG, bytes))
+}
+
+#[post("/project/<id>/attachments/<attachment_id>/delete")]
+fn delete_attachment_post(
+    id: u8,
+    attachment_id: i64,
+    user: User,
+    workspace: Workspace,
+    config: &State<AppConfig>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(project_id(id, _)));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can remove files");
+    }
+    let Ok(attachment) = query_attachment_by_id(id, attachment_id) else {
+        return Flash::error(redirect, "Attachment not found");
+    };
+    match delete_attachment(id, attachment_id) {
+        Ok(_) => {
+            let storage = storage_from_config(config);
+            let _ = storage.delete(&attachment.storage_key);
+            if let Some(thumbnail_key) = attachment.thumbnail_key {
+                let _ = storage.delete(&thumbnail_key);
+            }
+            Flash::success(redirect, format!("{} removed", attachment.file_name))
+        }
+        Err(_) => Flash::error(redirect, "Failed to remove attachment"),
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct InviteMemberForm<'v> {
+    email: &'v str,
+    role: ProjectRole,
+}
+
+#[derive(FromForm, Debug)]
+struct MemberRoleForm {
+    role: ProjectRole,
+}
+
+#[derive(FromForm, Debug)]
+struct TransferOwnershipForm {
+    user_id: u8,
+}
+
+// the project, if this user owns it
+fn owned_project(user: &User, workspace: &Workspace, project_id: u8) -> Option<Project> {
+    project_with_role(user, workspace, project_id)
+        .filter(|(_, role)| *role == ProjectRole::Owner)
+        .map(|(project, _)| project)
+}
+
+// a report as a file to download rather than open
+#[derive(Responder)]
+struct PdfFile {
+    inner: (ContentType, Vec<u8>),
+    disposition: Header<'static>,
+}
+
+fn pdf_file(name: &str, bytes: Vec<u8>) -> PdfFile {
+    PdfFile {
+        inner: (ContentType::PDF, bytes),
+        disposition: Header::new(
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}-{}.pdf\"",
+                name,
+                Local::now().format("%Y-%m-%d")
+            ),
+        ),
+    }
+}
+
+// one-page status report, laid out for printing
+#[get("/project/<id>/report")]
+fn project_report(id: u8, user: User, workspace: Workspace) -> Option<Template> {
+    let (project, _) = project_with_role(&user, &workspace, id)?;
+    match query_project_report(project) {
+        Ok(report) => Some(Template::render("report-project", context! {report})),
+        Err(_) => Some(Template::render(
+            "error",
+            context! {msg: "Failed to build the report."},
+        )),
+    }
+}
+
+// the same report as a PDF
+#[get("/project/<id>/report.pdf")]
+fn project_report_pdf_get(id: u8, user: User, workspace: Workspace) -> Option<PdfFile> {
+    let (project, _) = project_with_role(&user, &workspace, id)?;
+    let report = query_project_report(project).ok()?;
+    Some(pdf_file(
+        &format!("project-{}-report", id),
+        project_report_pdf(&report),
+    ))
+}
+
+// every project /all-projects would list with the same filters, laid out for printing
+#[get("/all-projects/report?<tag>&<list..>")]
+fn summary_report(
+    _admin: Admin,
+    workspace: Workspace,
+    tag: Option<Vec<String>>,
+    list: Option<ListQuery>,
+) -> Template {
+    let list = list.unwrap_or_default();
+    let selected_tags = selected_tags(tag);
+    let tagged = tagged_project_ids(workspace.id, &selected_tags);
+    match query_summary_report(workspace.id, &workspace.name, &list, tagged.as_deref()) {
+        Ok(report) => {
+            let query = list
+                .pager(report.total, PROJECT_SORTS, &tag_params(&selected_tags))
+                .query;
+            Template::render("report-summary", context! {report, query})
+        }
+        Err(_) => Template::render("error", context! {msg: "Failed to build the report."}),
+    }
+}
+
+// the same report as a PDF
+#[get("/all-projects/report.pdf?<tag>&<list..>")]
+fn summary_report_pdf_get(
+    _admin: Admin,
+    workspace: Workspace,
+    tag: Option<Vec<String>>,
+    list: Option<ListQuery>,
+) -> Option<PdfFile> {
+    let list = list.unwrap_or_default();
+    let tagged = tagged_project_ids(workspace.id, &selected_tags(tag));
+    let report =
+        query_summary_report(workspace.id, &workspace.name, &list, tagged.as_deref()).ok()?;
+    Some(pdf_file("projects-report", summary_report_pdf(&report)))
+}
+
+#[get("/project/<id>/members")]
+fn project_members(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    flash: Option<FlashMessage<'_>>,
+) -> Template {
+    let msg = get_flash_msg(flash);
+    let Some((project, role)) = project_with_role(&user, &workspace, id) else {
+        return Template::render("error", context! {msg: "Project not found."});
+    };
+    match query_project_members(id) {
+        Ok(members) => Template::render(
+            "project-members",
+            context! {user, project, role, members, msg},
+        ),
+        Err(_) => Template::render("error", context! {msg: "Failed to query members."}),
+    }
+}
+
+#[post("/project/<id>/members", data = "<form>")]
+fn invite_member_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<InviteMemberForm<'_>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(project_members(id)));
+    let Some(project) = owned_project(&user, &workspace, id) else {
+        return Flash::error(redirect, "Only the owner can invite members");
+    };
+    match add_project_member(id, form.email, form.role) {
+        Ok(member_id) => {
+            let _ = enqueue_notification(
+                member_id,
+                &format!(
+                    "{} added you to project '{}' as {}",
+                    user.email,
+                    project.name,
+                    form.role.as_str()
+                ),
+                &format!("/project/{}", id),
+            );
+            Flash::success(redirect, format!("{} added", form.email))
+        }
+        Err(_) => Flash::error(
+            redirect,
+            format!(
+                "Couldn't add {}: no such user, or already a member",
+                form.email
+            ),
+        ),
+    }
+}
+
+#[post("/project/<id>/members/<member_id>/role", data = "<form>")]
+fn member_role_post(
+    id: u8,
+    member_id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<MemberRoleForm>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(project_members(id)));
+    if owned_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only the owner can change roles");
+    }
+    match update_project_member_role(id, member_id, form.role) {
+        Ok(_) => Flash::success(redirect, "Role updated"),
+        Err(_) => Flash::error(redirect, "Failed to update role"),
+    }
+}
+
+// owners remove members; members can remove themselves to leave a project
+#[post("/project/<id>/members/<member_id>/remove")]
+fn remove_member_post(id: u8, member_id: u8, user: User, workspace: Workspace) -> Flash<Redirect> {
+    if member_id != user.id && owned_project(&user, &workspace, id).is_none() {
+        return Flash::error(
+            Redirect::to(uri!(project_members(id))),
+            "Only the owner can remove members",
+        );
+    }
+    match remove_project_member(id, member_id) {
+        Ok(_) if member_id == user.id => {
+            Flash::success(Redirect::to(uri!(profile(_, _))), "You left the project")
+        }
+        Ok(_) => Flash::success(Redirect::to(uri!(project_members(id))), "Member removed"),
+        Err(_) => Flash::error(
+            Redirect::to(uri!(project_members(id))),
+            "Failed to remove member",
+        ),
+    }
+}
+
+#[post("/project/<id>/transfer", data = "<form>")]
+fn transfer_ownership_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<TransferOwnershipForm>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(project_members(id)));
+    let Some(project) = owned_project(&user, &workspace, id) else {
+        return Flash::error(redirect, "Only the owner can transfer ownership");
+    };
+    match transfer_project_ownership(id, user.id, form.user_id) {
+        Ok(_) => {
+            let _ = enqueue_notification(
+                form.user_id,
+                &format!("{} made you the owner of '{}'", user.email, project.name),
+                &format!("/project/{}", id),
+            );
+            Flash::success(redirect, "Ownership transferred")
+        }
+        Err(_) => Flash::error(redirect, "Ownership can only go to an existing member"),
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct SwitchWorkspaceForm {
+    team_id: i64,
+}
+
+#[derive(FromForm, Debug)]
+struct AddWorkspaceForm<'v> {
+    name: &'v str,
+}
+
+#[derive(FromForm, Debug)]
+struct AddTeamMemberForm<'v> {
+    email: &'v str,
+    role: TeamRole,
+}
+
+#[derive(FromForm, Debug)]
+struct TeamMemberRoleForm {
+    role: TeamRole,
+}
+
+#[derive(FromForm, Debug)]
+struct InvitationForm {
+    role: TeamRole,
+    #[field(validate = range(1..=90))]
+    expires_in_days: u32,
+    // empty means unlimited
+    max_uses: Option<u32>,
+}
+
+// navigation fragment, loaded by htmx on every page
+#[get("/workspaces/switcher")]
+fn workspace_switcher(user: User, workspace: Workspace) -> Template {
+    let workspaces = query_workspaces_for_user(user.id).unwrap_or_default();
+    Template::render(
+        "partials/workspace-switcher",
+        context! {user, workspace, workspaces},
+    )
+}
+
+#[post("/workspaces/switch", data = "<form>")]
+fn switch_workspace_post(
+    user: User,
+    cookies: &CookieJar<'_>,
+    form: Form<SwitchWorkspaceForm>,
+) -> Flash<Redirect> {
+    match query_active_workspace(user.id, Some(form.team_id)) {
+        Ok(workspace) if workspace.id == form.team_id => {
+            cookies.add_private(Cookie::new("workspace_id", workspace.id.to_string()));
+            Flash::success(
+                Redirect::to(uri!(profile(_, _))),
+                format!("Switched to {}", workspace.name),
+            )
+        }
+        _ => Flash::error(Redirect::to(uri!(profile(_, _))), "Workspace not found"),
+    }
+}
+
+#[get("/workspaces")]
+fn workspaces_get(
+    user: User,
+    workspace: Workspace,
+    host: &Host<'_>,
+    config: &State<AppConfig>,
+    flash: Option<FlashMessage<'_>>,
+) -> Template {
+    let msg = get_flash_msg(flash);
+    // only admins hand out invitations, so only they get to see them
+    let invitations = if workspace.is_admin() {
+        query_invitations_for_team(workspace.id).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let invite_url = config.absolute_url(&host.to_string(), "/add-user?invite=");
+    let registration_closed = config.registration_mode == RegistrationMode::Closed;
+    match (
+        query_workspaces_for_user(user.id),
+        query_team_members(workspace.id),
+    ) {
+        (Ok(workspaces), Ok(members)) => Template::render(
+            "workspaces",
+            context! {
+                user, workspace, workspaces, members, invitations, invite_url, registration_closed, msg
+            },
+        ),
+        _ => Template::render("error", context! {msg: "Failed to query workspaces."}),
+    }
+}
+
+#[get("/workspaces", rank = 2)]
+fn workspaces_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /workspaces not logged in; redirecting to login",
+    )
+}
+
+// the new workspace becomes the active one
+#[post("/workspaces", data = "<form>")]
+fn add_workspace_post(
+    user: User,
+    cookies: &CookieJar<'_>,
+    form: Form<AddWorkspaceForm<'_>>,
+) -> Flash<Redirect> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Flash::error(
+            Redirect::to(uri!(workspaces_get())),
+            "A workspace needs a name",
+        );
+    }
+    match create_team(name, user.id) {
+        Ok(team_id) => {
+            cookies.add_private(Cookie::new("workspace_id", team_id.to_string()));
+            Flash::success(
+                Redirect::to(uri!(workspaces_get())),
+                format!("Created {}", name),
+            )
+        }
+        Err(_) => Flash::error(
+            Redirect::to(uri!(workspaces_get())),
+            "Failed to create workspace",
+        ),
+    }
+}
+
+// sends a single-use invitation rather than adding anyone outright; the answer is the same
+// whether or not the address has an account, so it can't be used to probe for them
+#[post("/workspaces/members", data = "<form>")]
+fn add_team_member_post(
+    user: User,
+    workspace: Workspace,
+    host: &Host<'_>,
+    config: &State<AppConfig>,
+    form: Form<AddTeamMemberForm<'_>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(workspaces_get()));
+    if !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can invite members");
+    }
+    let invitation = InvitationInput {
+        role: form.role,
+        expires_in_days: EMAILED_INVITATION_DAYS,
+        max_uses: Some(1),
+        email: Some(form.email.to_string()),
+    };
+    let Ok(token) = add_invitation(workspace.id, user.id, &invitation) else {
+        return Flash::error(redirect, "Failed to create invitation");
+    };
+    let path = format!("/add-user?invite={}", token);
+    if let Ok(invitee) = query_user_by_email(form.email.to_string()) {
+        let _ = enqueue_notification(
+            invitee.id,
+            &format!(
+                "{} invited you to the workspace '{}'",
+                user.email, workspace.name
+            ),
+            &path,
+        );
+    }
+    // without a mail backend the email would be dropped without an error
+    if config.mail_backend == MailBackend::None {
+        return Flash::success(
+            redirect,
+            format!(
+                "Mail isn't set up, so pass on the link under invitation links to {}",
+                form.email
+            ),
+        );
+    }
+    let email = invitation_email(
+        form.email,
+        &workspace.name,
+        &user.email,
+        &config.absolute_url(&host.to_string(), &path),
+    );
+    match mailer_from_config(config).send(&email) {
+        Ok(_) => Flash::success(redirect, format!("Invitation sent to {}", form.email)),
+        Err(_) => Flash::error(
+            redirect,
+            format!(
+                "Couldn't mail {}, pass on the link under invitation links instead",
+                form.email
+            ),
+        ),
+    }
+}
+
+#[post("/workspaces/members/<member_id>/role", data = "<form>")]
+fn team_member_role_post(
+    member_id: u8,
+    workspace: Workspace,
+    form: Form<TeamMemberRoleForm>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(workspaces_get()));
+    if !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can change roles");
+    }
+    match update_team_member_role(workspace.id, member_id, form.role) {
+        Ok(_) => Flash::success(redirect, "Role updated"),
+        Err(_) => Flash::error(redirect, "A workspace needs at least one admin"),
+    }
+}
+
+#[post("/workspaces/invitations", data = "<form>")]
+fn add_invitation_post(
+    user: User,
+    workspace: Workspace,
+    form: Form<Contextual<'_, InvitationForm>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(workspaces_get()));
+    if !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can invite people");
+    }
+    let Some(ref form_data) = form.value else {
+        return Flash::error(redirect, "Invitations expire after 1 to 90 days");
+    };
+    let invitation = InvitationInput {
+        role: form_data.role,
+        expires_in_days: form_data.expires_in_days,
+        max_uses: form_data.max_uses.filter(|uses| *uses > 0),
+        email: None,
+    };
+    match add_invitation(workspace.id, user.id, &invitation) {
+        Ok(_) => Flash::success(redirect, "Invitation link created"),
+        Err(_) => Flash::error(redirect, "Failed to create invitation"),
+    }
+}
+
+#[post("/workspaces/invitations/<invitation_id>/revoke")]
+fn revoke_invitation_post(invitation_id: i64, workspace: Workspace) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(workspaces_get()));
+    if !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can revoke invitations");
+    }
+    match revoke_invitation(workspace.id, invitation_id) {
+        Ok(_) => Flash::success(redirect, "Invitation revoked"),
+        Err(_) => Flash::error(redirect, "Invitation not found"),
+    }
+}
+
+// admins remove members; members can remove themselves to leave a workspace
+#[post("/workspaces/members/<member_id>/remove")]
+fn remove_team_member_post(
+    member_id: u8,
+    user: User,
+    workspace: Workspace,
+    cookies: &CookieJar<'_>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(workspaces_get()));
+    if member_id != user.id && !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can remove members");
+    }
+    match remove_team_member(workspace.id, member_id) {
+        Ok(_) if member_id == user.id => {
+            cookies.remove_private("workspace_id");
+            Flash::success(redirect, format!("You left {}", workspace.name))
+        }
+        Ok(_) => Flash::success(redirect, "Member removed"),
+        Err(_) => Flash::error(redirect, "Couldn't remove member, see the rules below"),
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct TagForm<'v> {
+    name: &'v str,
+    colour: &'v str,
+}
+
+impl<'v> TagForm<'v> {
+    // the trimmed name and colour, or what's wrong with them
+    fn validated(&self) -> Result<(&'v str, &'v str), &'static str> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("A tag needs a name");
+        }
+        if !is_valid_colour(self.colour) {
+            return Err("Colours look like #1e90ff");
+        }
+        Ok((name, self.colour))
+    }
+}
+
+#[get("/tags")]
+fn tags_get(user: User, workspace: Workspace, flash: Option<FlashMessage<'_>>) -> Negotiated {
+    let msg = get_flash_msg(flash);
+    match query_tags_for_team(workspace.id) {
+        Ok(tags) => Negotiated::new("tags", context! {user, workspace, tags, msg}),
+        Err(_) => Negotiated::error(Status::InternalServerError, "Failed to query tags."),
+    }
+}
+
+#[get("/tags", rank = 2)]
+fn tags_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /tags not logged in; redirecting to login",
+    )
+}
+
+// every member can add tags to the workspace
+#[post("/tags", data = "<form>")]
+fn add_tag_post(workspace: Workspace, form: Form<TagForm<'_>>) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(tags_get()));
+    let (name, colour) = match form.validated() {
+        Ok(tag) => tag,
+        Err(msg) => return Flash::error(redirect, msg),
+    };
+    match add_tag(workspace.id, name, colour) {
+        Ok(_) => Flash::success(redirect, format!("Added {}", name)),
+        Err(_) => Flash::error(redirect, format!("{} already exists", name)),
+    }
+}
+
+// renaming or removing a tag changes everyone's projects, so that's for admins
+#[post("/tags/<tag_id>", data = "<form>")]
+fn edit_tag_post(tag_id: i64, workspace: Workspace, form: Form<TagForm<'_>>) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(tags_get()));
+    if !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can change tags");
+    }
+    let (name, colour) = match form.validated() {
+        Ok(tag) => tag,
+        Err(msg) => return Flash::error(redirect, msg),
+    };
+    match edit_tag(workspace.id, tag_id, name, colour) {
+        Ok(_) => Flash::success(redirect, format!("Saved {}", name)),
+        Err(_) => Flash::error(redirect, "Failed to save tag; names have to be unique"),
+    }
+}
+
+#[post("/tags/<tag_id>/delete")]
+fn delete_tag_post(tag_id: i64, workspace: Workspace) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(tags_get()));
+    if !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can delete tags");
+    }
+    match delete_tag(workspace.id, tag_id) {
+        Ok(_) => Flash::success(redirect, "Tag deleted"),
+        Err(_) => Flash::error(redirect, "Tag not found"),
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct MoveCardForm {
+    project_id: u8,
+    status: ProjectStatus,
+    // the end of the column when left out
+    position: Option<usize>,
+}
+
+#[derive(FromForm, Debug)]
+struct BoardColumnForm {
+    status: ProjectStatus,
+    position: i64,
+    wip_limit: Option<u32>,
+}
+
+#[derive(FromForm, Debug)]
+struct BoardSettingsForm {
+    columns: Vec<BoardColumnForm>,
+}
+
+// the board as a fragment for htmx, with the message swapped into the flash
+fn board_swap(user: &User, workspace: &Workspace, msg: &str) -> Template {
+    let columns = query_board(user.id, workspace.id).unwrap_or_default();
+    Template::render(
+        "partials/board-columns",
+        context! {user, workspace, columns, msg, swap: true},
+    )
+}
+
+#[get("/board")]
+fn board_get(
+    user: User,
+    workspace: Workspace,
+    hx: Option<HxRequest>,
+    flash: Option<FlashMessage<'_>>,
+) -> Negotiated {
+    let msg = get_flash_msg(flash);
+    match query_board(user.id, workspace.id) {
+        Ok(columns) if hx.is_some() => Negotiated::new(
+            "partials/board-columns",
+            context! {user, workspace, columns},
+        ),
+        Ok(columns) => {
+            let statuses: Vec<&str> = ProjectStatus::ALL.iter().map(|s| s.as_str()).collect();
+            Negotiated::new("board", context! {user, workspace, columns, statuses, msg})
+        }
+        Err(_) => Negotiated::error(Status::InternalServerError, "Failed to query projects."),
+    }
+}
+
+#[get("/board", rank = 2)]
+fn board_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /board not logged in; redirecting to login",
+    )
+}
+
+// dropping a card on the board; htmx gets the updated board back
+#[post("/board/move", data = "<form>")]
+fn move_card_post(
+    user: User,
+    workspace: Workspace,
+    hx: Option<HxRequest>,
+    form: Form<MoveCardForm>,
+) -> Result<Template, Flash<Redirect>> {
+    let position = form.position.unwrap_or(usize::MAX);
+    let moved = move_card(&user, workspace.id, form.project_id, form.status, position);
+    match (moved, hx) {
+        (Ok(_), Some(_)) => Ok(board_swap(&user, &workspace, "")),
+        (Err(e), Some(_)) => Ok(board_swap(&user, &workspace, &e.to_string())),
+        (Ok(_), None) => Err(Flash::success(
+            Redirect::to(uri!(board_get())),
+            "Project moved",
+        )),
+        (Err(e), None) => Err(Flash::error(Redirect::to(uri!(board_get())), e.to_string())),
+    }
+}
+
+// column order and WIP limits are the same for the whole workspace
+#[post("/board/columns", data = "<form>")]
+fn board_columns_post(workspace: Workspace, form: Form<BoardSettingsForm>) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(board_get()));
+    if !workspace.is_admin() {
+        return Flash::error(redirect, "Only workspace admins can change the columns");
+    }
+    let mut columns: Vec<_> = form.columns.iter().collect();
+    columns.sort_by_key(|column| column.position);
+    let mut settings: Vec<ColumnSettings> = columns
+        .iter()
+        .map(|column| ColumnSettings {
+            status: column.status,
+            // 0 or nothing means no limit
+            wip_limit: column.wip_limit.filter(|limit| *limit > 0),
+        })
+        .collect();
+    // statuses left out of the form keep their place after the ones sent
+    for column in query_column_settings(workspace.id).unwrap_or_default() {
+        if !settings
+            .iter()
+            .any(|setting| setting.status == column.status)
+        {
+            settings.push(column);
+        }
+    }
+    match save_column_settings(workspace.id, &settings) {
+        Ok(_) => Flash::success(redirect, "Board columns saved"),
+        Err(_) => Flash::error(redirect, "Failed to save board columns"),
+    }
+}
+
+// your projects by default; admins can ask for the whole workspace with `all=true`
+#[get("/timeline?<zoom>&<all>")]
+fn timeline(
+    zoom: Option<Zoom>,
+    all: Option<bool>,
+    user: User,
+    admin: Option<Admin>,
+    workspace: Workspace,
+) -> Template {
+    let zoom = zoom.unwrap_or(Zoom::Month);
+    let all = all.unwrap_or(false) && admin.is_some();
+    let projects = if all {
+        query_all_projects(workspace.id)
+    } else {
+        query_all_projects_for_user(user.id, workspace.id)
+    };
+    let Ok(mut projects) = projects else {
+        return Template::render("error", context! {msg: "Failed to query projects."});
+    };
+    projects.sort_by(|a, b| a.start_date.cmp(&b.start_date).then(a.name.cmp(&b.name)));
+    let milestones: Vec<_> = query_milestones_for_team(workspace.id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|milestone| {
+            projects
+                .iter()
+                .any(|project| project.id_proj == Some(milestone.project_id))
+        })
+        .collect();
+    let svg = render_gantt(&projects, &milestones, zoom, Local::now().date_naive());
+    let zooms: Vec<&str> = Zoom::ALL.iter().map(|zoom| zoom.as_str()).collect();
+    let context = context! {
+        user, workspace, svg, zooms, all, zoom: zoom.as_str(), can_see_all: admin.is_some()
+    };
+    Template::render("timeline", context)
+}
+
+#[get("/timeline", rank = 2)]
+fn timeline_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /timeline not logged in; redirecting to login",
+    )
+}
+
+#[get("/search?<q>")]
+fn search_get(q: Option<&str>, user: User, workspace: Workspace) -> Negotiated {
+    let q = q.unwrap_or_default().trim();
+    match search(q, workspace.id, user.id, MAX_RESULTS) {
+        Ok(results) => Negotiated::new("search", context! {user, workspace, q, results}),
+        Err(_) => Negotiated::error(Status::InternalServerError, "Search failed."),
+    }
+}
+
+#[get("/search", rank = 2)]
+fn search_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /search not logged in; redirecting to login",
+    )
+}
+
+// the same search for scripts: `GET /api/v1/search?q=launch&limit=10`
+#[get("/api/v1/search?<q>&<limit>")]
+fn api_search(
+    q: &str,
+    limit: Option<usize>,
+    user: User,
+    workspace: Workspace,
+) -> Result<Json<Vec<SearchResult>>, Status> {
+    search(q, workspace.id, user.id, limit.unwrap_or(20))
+        .map(Json)
+        .map_err(|_| Status::InternalServerError)
+}
+
+#[get("/api/v1/search", rank = 2)]
+fn api_search_no_auth() -> Status {
+    Status::Unauthorized
+}
+
+// `month` is "2024-05"; this month when left out
+#[get("/calendar?<month>")]
+fn calendar_get(
+    month: Option<&str>,
+    user: User,
+    workspace: Workspace,
+    host: &Host<'_>,
+    config: &State<AppConfig>,
+    flash: Option<FlashMessage<'_>>,
+) -> Negotiated {
+    let msg = get_flash_msg(flash);
+    let today = Local::now().date_naive();
+    let first = month
+        .and_then(parse_month)
+        .unwrap_or_else(|| today.with_day(1).unwrap_or(today));
+    let Ok(projects) = query_all_projects_for_user(user.id, workspace.id) else {
+        return Negotiated::error(Status::InternalServerError, "Failed to query projects.");
+    };
+    let weeks = month_grid(first, &projects, today);
+    let previous = (first - Duration::days(1)).format("%Y-%m").to_string();
+    let next = (first + Duration::days(31)).format("%Y-%m").to_string();
+    let feed_url = query_calendar_token(user.id).ok().flatten().map(|token| {
+        config.absolute_url(&host.to_string(), &format!("/calendar/feed/{}.ics", token))
+    });
+    let context = context! {
+        user, workspace, weeks, previous, next, feed_url, msg,
+        month: first.format("%Y-%m").to_string(),
+        month_name: first.format("%B %Y").to_string(),
+    };
+    Negotiated::new("calendar", context)
+}
+
+#[get("/calendar", rank = 2)]
+fn calendar_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /calendar not logged in; redirecting to login",
+    )
+}
+
+// a new feed URL, so a leaked one can be shut off
+#[post("/calendar/feed")]
+fn regenerate_calendar_feed_post(user: User) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(calendar_get(_)));
+    match regenerate_calendar_token(user.id) {
+        Ok(_) => Flash::success(
+            redirect,
+            "New feed URL created; the old one no longer works",
+        ),
+        Err(_) => Flash::error(redirect, "Failed to create a feed URL"),
+    }
+}
+
+// no login here, calendar apps only have the URL; the token in it stands in for the user
+#[get("/calendar/feed/<file>")]
+fn calendar_feed(file: &str) -> Option<(ContentType, String)> {
+    let token = file.strip_suffix(".ics")?;
+    let user_id = query_calendar_token_user(token).ok()??;
+    // every workspace the user is in, since the feed isn't tied to a session
+    let projects: Vec<_> = query_workspaces_for_user(user_id)
+        .ok()?
+        .iter()
+        .flat_map(|workspace| {
+            query_all_projects_for_user(user_id, workspace.id).unwrap_or_default()
+        })
+        .collect();
+    Some((ContentType::Calendar, render_ics(&projects)))
+}
+
+#[derive(FromForm, Debug)]
+struct MilestoneForm<'v> {
+    title: &'v str,
+    due_date: &'v str,
+}
+
+#[derive(FromForm, Debug)]
+struct MilestoneStatusForm {
+    completed: bool,
+}
+
+#[derive(FromForm, Debug)]
+struct DependencyForm {
+    depends_on_id: u8,
+}
+
+#[post("/project/<id>/milestones", data = "<form>")]
+fn add_milestone_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<MilestoneForm<'_>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(format!("{}#milestones", uri!(project_id(id, _))));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can add milestones");
+    }
+    let title = form.title.trim();
+    if title.is_empty() {
+        return Flash::error(redirect, "A milestone needs a title");
+    }
+    let due_date = match milestone_due_date(form.due_date) {
+        Ok(due_date) => due_date,
+        Err(msg) => return Flash::error(redirect, msg),
+    };
+    match add_milestone(id, title, &due_date) {
+        Ok(_) => Flash::success(redirect, format!("Added {}", title)),
+        Err(_) => Flash::error(redirect, "Failed to add milestone"),
+    }
+}
+
+#[post("/project/<id>/milestones/<milestone_id>", data = "<form>")]
+fn milestone_status_post(
+    id: u8,
+    milestone_id: i64,
+    user: User,
+    workspace: Workspace,
+    form: Form<MilestoneStatusForm>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(format!("{}#milestones", uri!(project_id(id, _))));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can update milestones");
+    }
+    match set_milestone_completed(id, milestone_id, form.completed) {
+        Ok(_) if form.completed => Flash::success(redirect, "Milestone reached"),
+        Ok(_) => Flash::success(redirect, "Milestone reopened"),
+        Err(_) => Flash::error(redirect, "Milestone not found"),
+    }
+}
+
+#[post("/project/<id>/milestones/<milestone_id>/delete")]
+fn delete_milestone_post(
+    id: u8,
+    milestone_id: i64,
+    user: User,
+    workspace: Workspace,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(format!("{}#milestones", uri!(project_id(id, _))));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can remove milestones");
+    }
+    match delete_milestone(id, milestone_id) {
+        Ok(_) => Flash::success(redirect, "Milestone removed"),
+        Err(_) => Flash::error(redirect, "Milestone not found"),
+    }
+}
+
+#[post("/project/<id>/dependencies", data = "<form>")]
+fn add_dependency_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<DependencyForm>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(format!("{}#dependencies", uri!(project_id(id, _))));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can change dependencies");
+    }
+    // the other project has to be one this user can see
+    if project_with_role(&user, &workspace, form.depends_on_id).is_none() {
+        return Flash::error(redirect, "Project not found");
+    }
+    match add_dependency(id, form.depends_on_id, workspace.id) {
+        Ok(_) => Flash::success(redirect, "Dependency added"),
+        Err(e) => Flash::error(redirect, e.to_string()),
+    }
+}
+
+#[post("/project/<id>/dependencies/<depends_on_id>/delete")]
+fn remove_dependency_post(
+    id: u8,
+    depends_on_id: u8,
+    user: User,
+    workspace: Workspace,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(format!("{}#dependencies", uri!(project_id(id, _))));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can change dependencies");
+    }
+    match remove_dependency(id, depends_on_id) {
+        Ok(_) => Flash::success(redirect, "Dependency removed"),
+        Err(_) => Flash::error(redirect, "Dependency not found"),
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct TimeEntryForm<'v> {
+    started_at: &'v str,
+    ended_at: &'v str,
+    duration_minutes: Option<u32>,
+    note: &'v str,
+}
+
+#[derive(FromForm, Debug)]
+struct TimerForm<'v> {
+    note: Option<&'v str>,
+}
+
+#[derive(Responder)]
+struct CsvFile {
+    inner: (ContentType, String),
+    disposition: Header<'static>,
+}
+
+fn parse_day(date: Option<&str>) -> Option<NaiveDate> {
+    date.and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+#[post("/project/<id>/time", data = "<form>")]
+fn add_time_entry_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<TimeEntryForm<'_>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(format!("{}#time", uri!(project_id(id, _))));
+    if editable_project(&user, &workspace, id).is_none() {
+        return Flash::error(redirect, "Only owners and editors can log time");
+    }
+    let (started_at, ended_at) =
+        match entry_times(form.started_at, form.ended_at, form.duration_minutes) {
+            Ok(times) => times,
+            Err(msg) => return Flash::error(redirect, msg),
+        };
+    match add_time_entry(id, user.id, &started_at, &ended_at, form.note.trim()) {
+        Ok(_) => Flash::success(redirect, "Time logged"),
+        Err(_) => Flash::error(redirect, "Failed to log time"),
+    }
+}
+
+#[post("/project/<id>/timer/start", data = "<form>")]
+fn start_timer_post(
+    id: u8,
+    user: User,
+    workspace: Workspace,
+    form: Form<TimerForm<'_>>,
+) -> Flash<Redirect> {
+    let redirect = Redirect::to(format!("{}#time", uri!(project_id(id, _))));
+    let Some(project) = editable_project(&user, &workspace, id) else {
+        return Flash::error(redirect, "Only owners and editors can log time");
+    };
+    match start_timer(id, user.id, form.note.unwrap_or_default().trim()) {
+        Ok(_) => Flash::success(redirect, format!("Timer running on {}", project.name)),
+        Err(_) => Flash::error(redirect, "Failed to start timer"),
+    }
+}
+
+// back to the project the timer was running on
+#[post("/timer/stop")]
+fn stop_timer_post(user: User) -> Flash<Redirect> {
+    let Ok(Some(running)) = query_running_timer(user.id) else {
+        return Flash::error(Redirect::to(uri!(timesheet(_))), "No timer is running");
+    };
+    let redirect = Redirect::to(format!("{}#time", uri!(project_id(running.project_id, _))));
+    match stop_timer(user.id) {
+        Ok(_) => Flash::success(redirect, "Timer stopped"),
+        Err(_) => Flash::error(redirect, "Failed to stop timer"),
+    }
+}
+
+#[post("/time/<entry_id>/delete?<week>")]
+fn delete_time_entry_post(entry_id: i64, week: Option<&str>, user: User) -> Flash<Redirect> {
+    let redirect = Redirect::to(uri!(timesheet(week)));
+    match delete_time_entry(user.id, entry_id) {
+        Ok(_) => Flash::success(redirect, "Entry deleted"),
+        Err(_) => Flash::error(redirect, "Entry not found"),
+    }
+}
+
+// your entries in the active workspace for the week containing `week` (default: this week)
+#[get("/timesheet?<week>")]
+fn timesheet(
+    week: Option<&str>,
+    user: User,
+    workspace: Workspace,
+    flash: Option<FlashMessage<'_>>,
+) -> Negotiated {
+    let msg = get_flash_msg(flash);
+    let monday = week_start(parse_day(week).unwrap_or_else(|| Local::now().date_naive()));
+    let sunday = monday + Duration::days(6);
+    let Ok(entries) = query_time_entries_between(
+        workspace.id,
+        Some(user.id),
+        monday,
+        monday + Duration::days(7),
+    ) else {
+        return Negotiated::error(Status::InternalServerError, "Failed to query time entries.");
+    };
+    let rows = timesheet_rows(&entries, monday);
+    let days: Vec<String> = (0..7)
+        .map(|day| (monday + Duration::days(day)).format("%a %d").to_string())
+        .collect();
+    let day_totals: Vec<i64> = (0..7)
+        .map(|day| rows.iter().map(|row| row.days[day]).sum())
+        .collect();
+    let week_total: i64 = day_totals.iter().sum();
+    let running_timer = query_running_timer(user.id).ok().flatten();
+    let context = context! {
+        user, workspace, entries, rows, days, day_totals, week_total, running_timer, msg,
+        week: monday.format("%Y-%m-%d").to_string(),
+        week_end: sunday.format("%Y-%m-%d").to_string(),
+        previous_week: (monday - Duration::days(7)).format("%Y-%m-%d").to_string(),
+        next_week: (monday + Duration::days(7)).format("%Y-%m-%d").to_string(),
+    };
+    Negotiated::new("timesheet", context)
+}
+
+#[get("/timesheet", rank = 2)]
+fn timesheet_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /timesheet not logged in; redirecting to login",
+    )
+}
+
+// `from` and `to` are inclusive days; admins get everyone's entries in the workspace
+#[get("/time/export?<from>&<to>")]
+fn export_time_entries(
+    from: Option<&str>,
+    to: Option<&str>,
+    user: User,
+    workspace: Workspace,
+) -> Result<CsvFile, Flash<Redirect>> {
+    let today = Local::now().date_naive();
+    let from = parse_day(from).unwrap_or_else(|| week_start(today));
+    let to = parse_day(to).unwrap_or(today);
+    let redirect = Redirect::to(uri!(timesheet(Some(from.format("%Y-%m-%d").to_string()))));
+    if to < from {
+        return Err(Flash::error(
+            redirect,
+            "The range has to end after it starts",
+        ));
+    }
+    let user_id = (!workspace.is_admin()).then_some(user.id);
+    match query_time_entries_between(workspace.id, user_id, from, to + Duration::days(1)) {
+        Ok(entries) => Ok(CsvFile {
+            inner: (ContentType::CSV, time_entries_csv(&entries)),
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"time-{}-{}.csv\"", from, to),
+            ),
+        }),
+        Err(_) => Err(Flash::error(redirect, "Failed to export time entries")),
+    }
+}
+
+#[get("/settings/notifications")]
+fn notification_settings_get(
+    user: User,
+    config: &State<AppConfig>,
+    flash: Option<FlashMessage<'_>>,
+) -> Template {
+    let msg = get_flash_msg(flash);
+    let reminder_days = config.deadline_reminder_days;
+    match query_notification_preference(user.id) {
+        Ok(preference) => Template::render(
+            "notification-settings",
+            context! {user, preference, reminder_days, msg},
+        ),
+        Err(_) => Template::render(
+            "error",
+            context! {msg: "Failed to query notification settings."},
+        ),
+    }
+}
+
+#[get("/settings/notifications", rank = 2)]
+fn notification_settings_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /settings/notifications not logged in; redirecting to login",
+    )
+}
+
+#[post("/settings/notifications", data = "<form>")]
+fn notification_settings_post(
+    user: User,
+    form: Form<NotificationSettingsForm>,
+) -> Result<Flash<Redirect>, Template> {
+    let preference = NotificationPreference {
+        user_id: user.id,
+        deadline_in_app: form.deadline_in_app,
+        deadline_email: form.deadline_email,
+    };
+    match save_notification_preference(&preference) {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(uri!(notification_settings_get())),
+            "Notification settings saved",
+        )),
+        Err(_) => Err(Template::render(
+            "error",
+            context! {msg: "Failed to save notification settings."},
+        )),
+    }
+}
+
+#[get("/notifications")]
+fn notifications_get(user: User, flash: Option<FlashMessage<'_>>) -> Template {
+    let msg = get_flash_msg(flash);
+    match query_notifications_for_user(user.id) {
+        Ok(notifications) => {
+            let unread_count = notifications.iter().filter(|n| n.read_at.is_none()).count();
+            Template::render(
+                "notifications",
+                context! {notifications, unread_count, user, msg},
+            )
+        }
+        Err(_) => Template::render("error", context! {msg: "Failed to query notifications."}),
+    }
+}
+
+#[get("/notifications", rank = 2)]
+fn notifications_no_auth() -> Flash<Redirect> {
+    Flash::success(
+        Redirect::to(uri!(login_get_no_auth())),
+        "user from /notifications not logged in; redirecting to login",
+    )
+}
+
+// polled by the navigation in base.html.tera
+#[get("/notifications/badge")]
+fn notifications_badge(user: User) -> RawHtml<String> {
+    match count_unread_notifications(user.id) {
+        Ok(count) if count > 0 => RawHtml(format!("<sup><mark>{}</mark></sup>", count)),
+        _ => RawHtml(String::new()),
+    }
+}
+
+// marks the notification as read on the way to whatever it points at
+#[get("/notifications/<id>")]
+fn notification_open(user: User, id: i64) -> Result<Redirect, Template> {
+    match query_notification_by_id(id, user.id) {
+        Ok(notification) => {
+            let _ = mark_notification_read(id, user.id);
+            if notification.link.is_empty() {
+                Ok(Redirect::to(uri!(notifications_get())))
+            } else {
+                Ok(Redirect::to(notification.link))
+            }
+        }
+        Err(_) => Err(Template::render(
+            "error",
+            context! {msg: "Notification not found."},
+        )),
+    }
+}
+
+#[post("/notifications/<id>/read")]
+fn notification_mark_read(user: User, id: i64) -> Flash<Redirect> {
+    match mark_notification_read(id, user.id) {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!(notifications_get())),
+            "Notification marked as read",
+        ),
+        Err(_) => Flash::error(
+            Redirect::to(uri!(notifications_get())),
+            "Notification not found",
+        ),
+    }
+}
+
+#[post("/notifications/read-all")]
+fn notifications_mark_all_read(user: User) -> Flash<Redirect> {
+    match mark_all_notifications_read(user.id) {
+        Ok(count) => Flash::success(
+            Redirect::to(uri!(notifications_get())),
+            format!("{} notifications marked as read", count),
+        ),
+        Err(_) => Flash::error(
+            Redirect::to(uri!(notifications_get())),
+            "Failed to mark notifications as read",
+        ),
+    }
+}
+
+// project created/edited/deleted events for the projects this user may see
+#[get("/events/projects")]
+fn project_events(user: User, workspace: Workspace, mut shutdown: Shutdown) -> EventStream![] {
+    let mut receiver = subscribe_project_events();
+    EventStream! {
+        loop {
+            let event = select! {
+                message = receiver.recv() => match message {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+            if event.visible_to(&user, &workspace) {
+                yield Event::json(&event).event(event.event_name());
+            }
+        }
+    }
+}
+
+#[catch(404)]
+fn not_found() -> Template {
+    Template::render("catchers/404", context! {})
+}
+
+#[catch(500)]
+fn server_error() -> Template {
+    Template::render("catchers/500", context! {})
+}
+
+#[launch]
+fn rocket() -> _ {
+    // Rocket's own upload limits follow `max_upload_bytes`, leaving room for the other form fields
+    let figment = rocket::Config::figment();
+    let max_upload_bytes = figment
+        .extract::<AppConfig>()
+        .unwrap_or_default()
+        .max_upload_bytes;
+    let limits = figment
+        .extract_inner::<Limits>("limits")
+        .unwrap_or_default()
+        .limit("file", max_upload_bytes.bytes())
+        .limit("data-form", (max_upload_bytes + 64 * 1024).bytes());
+
+    rocket::custom(figment.merge(("limits", limits)))
+        .mount(
+            "/",
+            routes![
+                egg,
+                json_route,
+                index,
+                index_no_auth,
+                profile,
+                profile_no_auth,
+                login_get,
+                login_get_no_auth,
                 login_post,
                 logout,
                 user_id,
@@ -426,14 +3153,122 @@ fn rocket() -> _ {
                 delete_project,
                 all_users,
                 all_projects,
+                analytics,
+                project_report,
+                project_report_pdf_get,
+                summary_report,
+                summary_report_pdf_get,
+                import_get,
+                import_post,
+                export_own_projects,
+                export_all_projects,
+                export_all_users,
+                notification_settings_get,
+                notification_settings_no_auth,
+                notification_settings_post,
+                notifications_get,
+                notifications_no_auth,
+                notifications_badge,
+                notification_open,
+                notification_mark_read,
+                notifications_mark_all_read,
+                project_events,
+                project_tasks,
+                add_task_post,
+                edit_task_get,
+                edit_task_post,
+                delete_task_post,
+                move_task_post,
+                project_members,
+                invite_member_post,
+                member_role_post,
+                remove_member_post,
+                transfer_ownership_post,
+                upload_attachment_post,
+                download_attachment,
+                attachment_thumbnail,
+                delete_attachment_post,
+                board_get,
+                board_no_auth,
+                move_card_post,
+                board_columns_post,
+                timeline,
+                timeline_no_auth,
+                save_filter_post,
+                delete_filter_post,
+                add_widget_post,
+                delete_widget_post,
+                search_get,
+                search_no_auth,
+                api_search,
+                api_search_no_auth,
+                calendar_get,
+                calendar_no_auth,
+                regenerate_calendar_feed_post,
+                calendar_feed,
+                add_milestone_post,
+                milestone_status_post,
+                delete_milestone_post,
+                add_dependency_post,
+                remove_dependency_post,
+                add_time_entry_post,
+                start_timer_post,
+                stop_timer_post,
+                delete_time_entry_post,
+                timesheet,
+                timesheet_no_auth,
+                export_time_entries,
+                add_comment_post,
+                edit_comment_get,
+                edit_comment_post,
+                delete_comment_post,
+                workspace_switcher,
+                switch_workspace_post,
+                workspaces_get,
+                workspaces_no_auth,
+                add_workspace_post,
+                tags_get,
+                tags_no_auth,
+                add_tag_post,
+                edit_tag_post,
+                delete_tag_post,
+                add_team_member_post,
+                accept_invitation_post,
+                team_member_role_post,
+                remove_team_member_post,
+                add_invitation_post,
+                revoke_invitation_post,
             ],
         )
         .register("/", catchers![not_found, server_error])
-        .attach(Template::fairing())
+        .attach(Template::custom(|engines| {
+            engines.tera.register_filter("markdown", markdown_filter);
+            engines.tera.register_filter("duration", duration_filter);
+        }))
+        .attach(AdHoc::config::<AppConfig>())
+        .attach(AdHoc::try_on_ignite("Database Migrations", |rocket| {
+            Box::pin(async move {
+                match run_migrations() {
+                    Ok(_) => Ok(rocket),
+                    Err(e) => {
+                        println!("Failed to run database migrations: {}", e);
+                        Err(rocket)
+                    }
+                }
+            })
+        }))
         .mount("/", FileServer::from(relative!("static")))
+        .attach(AdHoc::on_liftoff("Deadline Reminders", |rocket| {
+            Box::pin(async move {
+                if let Some(config) = rocket.state::<AppConfig>() {
+                    rocket::tokio::spawn(remind_deadlines(config.clone()));
+                }
+            })
+        }))
         .attach(AdHoc::on_liftoff("Liftoff Message", |_| {
             Box::pin(async move {
-                print_hello();
+                // `print_hello` never returns, so keep it off the async workers
+                rocket::tokio::task::spawn_blocking(print_hello);
             })
         }))
 }
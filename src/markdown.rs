@@ -0,0 +1,21 @@
+use pulldown_cmark::{html, Options, Parser};
+use rocket_dyn_templates::tera::{self, Value};
+use std::collections::HashMap;
+
+// user-written Markdown to HTML that is safe to embed in a page
+pub fn render_markdown(source: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(source, options));
+    ammonia::clean(&unsafe_html)
+}
+
+// `{{ project.description | markdown | safe }}` in templates
+pub fn markdown_filter(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let source = tera::try_get_value!("markdown", "value", String, value);
+    Ok(Value::String(render_markdown(&source)))
+}
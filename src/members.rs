@@ -0,0 +1,130 @@
+use crate::db_queries::{user_project_role, ProjectRole};
+use crate::serialise::serialise_data;
+use rusqlite::{params, Connection, Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectMember {
+    pub project_id: u8,
+    pub user_id: u8,
+    pub email: String,
+    pub role: ProjectRole,
+}
+
+// owner first, then editors and viewers
+pub fn query_project_members(project_id: u8) -> Result<Vec<ProjectMember>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT project_member.project_id, project_member.user_id, user.email,
+                project_member.role
+            FROM project_member
+            JOIN user ON project_member.user_id = user.id
+            WHERE project_member.project_id = ?1
+            ORDER BY CASE project_member.role
+                WHEN 'owner' THEN 0 WHEN 'editor' THEN 1 ELSE 2 END, user.email",
+    )?;
+    let items_iter = statement.query_map(params![project_id], |row| {
+        Ok(ProjectMember {
+            project_id: row.get(0)?,
+            user_id: row.get(1)?,
+            email: row.get(2)?,
+            role: row.get(3)?,
+        })
+    })?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// invites a member of the project's team by email; returns their id so they can be notified
+pub fn add_project_member(project_id: u8, email: &str, role: ProjectRole) -> Result<u8, Error> {
+    if role == ProjectRole::Owner {
+        return Err(Error::InvalidQuery);
+    }
+    let conn = Connection::open("db.sqlite")?;
+    let user_id: u8 = conn.query_row(
+        "SELECT user.id FROM user
+            JOIN team_member ON team_member.user_id = user.id
+            JOIN project ON project.team_id = team_member.team_id
+            WHERE user.email = ?1 AND project.id_proj = ?2",
+        params![email, project_id],
+        |row| row.get(0),
+    )?;
+    if user_project_role(&conn, user_id, project_id)?.is_some() {
+        return Err(Error::InvalidQuery);
+    }
+    conn.execute(
+        "INSERT INTO project_member (project_id, user_id, role) VALUES (?1, ?2, ?3)",
+        params![project_id, user_id, role],
+    )?;
+    Ok(user_id)
+}
+
+// the owner's role only changes through `transfer_project_ownership`
+pub fn update_project_member_role(
+    project_id: u8,
+    user_id: u8,
+    role: ProjectRole,
+) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    if role == ProjectRole::Owner
+        || user_project_role(&conn, user_id, project_id)? == Some(ProjectRole::Owner)
+    {
+        return Err(Error::InvalidQuery);
+    }
+    let updated = conn.execute(
+        "UPDATE project_member SET role = ?1 WHERE project_id = ?2 AND user_id = ?3",
+        params![role, project_id, user_id],
+    )?;
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn remove_project_member(project_id: u8, user_id: u8) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    if user_project_role(&conn, user_id, project_id)? == Some(ProjectRole::Owner) {
+        return Err(Error::InvalidQuery);
+    }
+    let deleted = conn.execute(
+        "DELETE FROM project_member WHERE project_id = ?1 AND user_id = ?2",
+        params![project_id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// the new owner has to be a member already; the previous owner stays on as an editor
+pub fn transfer_project_ownership(
+    project_id: u8,
+    from_user_id: u8,
+    to_user_id: u8,
+) -> Result<(), Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+
+    if user_project_role(&tx, from_user_id, project_id)? != Some(ProjectRole::Owner) {
+        return Err(Error::InvalidQuery);
+    }
+    match user_project_role(&tx, to_user_id, project_id)? {
+        Some(ProjectRole::Editor) | Some(ProjectRole::Viewer) => {}
+        _ => return Err(Error::InvalidQuery),
+    }
+
+    tx.execute(
+        "UPDATE project SET user_id = ?1 WHERE id_proj = ?2",
+        params![to_user_id, project_id],
+    )?;
+    tx.execute(
+        "UPDATE project_member SET role = ?1 WHERE project_id = ?2 AND user_id = ?3",
+        params![ProjectRole::Owner, project_id, to_user_id],
+    )?;
+    tx.execute(
+        "INSERT INTO project_member (project_id, user_id, role) VALUES (?1, ?2, ?3)
+            ON CONFLICT (project_id, user_id) DO UPDATE SET role = excluded.role",
+        params![project_id, from_user_id, ProjectRole::Editor],
+    )?;
+    tx.commit()
+}
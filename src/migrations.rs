@@ -0,0 +1,306 @@
+use rusqlite::{Connection, Error};
+
+// every entry runs once, in order; `PRAGMA user_version` stores how many have been applied
+const MIGRATIONS: &[&str] = &[
+    // 1: base tables, only created when starting from an empty database
+    "CREATE TABLE IF NOT EXISTS user (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        email TEXT NOT NULL UNIQUE,
+        password TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        admin BOOLEAN NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS project (
+        id_proj INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        start_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+        end_date TEXT NOT NULL DEFAULT '',
+        user_id INTEGER NOT NULL REFERENCES user (id)
+    );",
+    // 2: deadline reminders
+    "CREATE TABLE notification (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        message TEXT NOT NULL,
+        link TEXT NOT NULL DEFAULT '',
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE notification_preference (
+        user_id INTEGER PRIMARY KEY REFERENCES user (id) ON DELETE CASCADE,
+        deadline_in_app BOOLEAN NOT NULL DEFAULT 1,
+        deadline_email BOOLEAN NOT NULL DEFAULT 0
+    );
+    CREATE TABLE deadline_reminder (
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        end_date TEXT NOT NULL,
+        sent_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (project_id, kind, end_date)
+    );",
+    // 3: notification centre
+    "ALTER TABLE notification ADD COLUMN read_at DATETIME;
+    CREATE INDEX notification_user_unread ON notification (user_id, read_at);",
+    // 4: explicit status, priority, due date and description on projects;
+    // until now a project counted as complete once it had an end_date
+    "ALTER TABLE project ADD COLUMN description TEXT NOT NULL DEFAULT '';
+    ALTER TABLE project ADD COLUMN status TEXT NOT NULL DEFAULT 'planned';
+    ALTER TABLE project ADD COLUMN priority TEXT NOT NULL DEFAULT 'medium';
+    ALTER TABLE project ADD COLUMN due_date TEXT NOT NULL DEFAULT '';
+    UPDATE project SET status = CASE WHEN end_date != '' THEN 'done' ELSE 'active' END;
+    ALTER TABLE deadline_reminder RENAME COLUMN end_date TO due_date;",
+    // 5: tasks inside projects
+    "CREATE TABLE task (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        title TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'todo',
+        assignee_id INTEGER REFERENCES user (id) ON DELETE SET NULL,
+        due_date TEXT NOT NULL DEFAULT '',
+        position INTEGER NOT NULL DEFAULT 0,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX task_project ON task (project_id, position);",
+    // 6: project collaborators; every existing owner becomes a member
+    "CREATE TABLE project_member (
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        role TEXT NOT NULL DEFAULT 'viewer',
+        added_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (project_id, user_id)
+    );
+    CREATE INDEX project_member_user ON project_member (user_id);
+    INSERT INTO project_member (project_id, user_id, role)
+        SELECT id_proj, user_id, 'owner' FROM project;",
+    // 7: teams; everything that already exists moves into one shared workspace
+    "CREATE TABLE team (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE team_member (
+        team_id INTEGER NOT NULL REFERENCES team (id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        role TEXT NOT NULL DEFAULT 'member',
+        added_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (team_id, user_id)
+    );
+    CREATE INDEX team_member_user ON team_member (user_id);
+    ALTER TABLE project ADD COLUMN team_id INTEGER REFERENCES team (id) ON DELETE CASCADE;
+    CREATE INDEX project_team ON project (team_id);
+    INSERT INTO team (name) SELECT 'Default' WHERE EXISTS (SELECT 1 FROM user);
+    INSERT INTO team_member (team_id, user_id, role)
+        SELECT team.id, user.id, CASE WHEN user.admin THEN 'admin' ELSE 'member' END
+            FROM team, user;
+    UPDATE team_member SET role = 'admin'
+        WHERE user_id = (SELECT MIN(id) FROM user)
+            AND NOT EXISTS (SELECT 1 FROM team_member WHERE role = 'admin');
+    UPDATE project SET team_id = (SELECT id FROM team);",
+    // 8: invitation links
+    "CREATE TABLE invitation (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        token TEXT NOT NULL UNIQUE,
+        team_id INTEGER NOT NULL REFERENCES team (id) ON DELETE CASCADE,
+        role TEXT NOT NULL DEFAULT 'member',
+        created_by INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        expires_at DATETIME NOT NULL,
+        max_uses INTEGER,
+        uses INTEGER NOT NULL DEFAULT 0,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX invitation_team ON invitation (team_id);",
+    // 9: project comments
+    "CREATE TABLE comment (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        parent_id INTEGER REFERENCES comment (id) ON DELETE CASCADE,
+        body TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        edited_at DATETIME,
+        deleted_at DATETIME
+    );
+    CREATE INDEX comment_project ON comment (project_id, parent_id);",
+    // 10: project attachments; the files themselves live in the configured storage
+    "CREATE TABLE attachment (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        file_name TEXT NOT NULL,
+        content_type TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        storage_key TEXT NOT NULL UNIQUE,
+        thumbnail_key TEXT,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX attachment_project ON attachment (project_id);",
+    // 11: workspace tags and the projects carrying them
+    "CREATE TABLE tag (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        team_id INTEGER NOT NULL REFERENCES team (id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        colour TEXT NOT NULL DEFAULT '#6c757d',
+        UNIQUE (team_id, name)
+    );
+    CREATE TABLE project_tag (
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tag (id) ON DELETE CASCADE,
+        PRIMARY KEY (project_id, tag_id)
+    );
+    CREATE INDEX project_tag_tag ON project_tag (tag_id);",
+    // 12: time tracking; an entry without an end is a running timer, one per user
+    "CREATE TABLE time_entry (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        started_at DATETIME NOT NULL,
+        ended_at DATETIME,
+        note TEXT NOT NULL DEFAULT '',
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX time_entry_project ON time_entry (project_id);
+    CREATE INDEX time_entry_user ON time_entry (user_id, started_at);
+    CREATE UNIQUE INDEX time_entry_running ON time_entry (user_id) WHERE ended_at IS NULL;",
+    // 13: milestones within a project, and projects blocked by other projects
+    "CREATE TABLE milestone (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        title TEXT NOT NULL,
+        due_date DATETIME NOT NULL,
+        completed_at DATETIME,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX milestone_project ON milestone (project_id, due_date);
+    CREATE TABLE project_dependency (
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        depends_on_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        PRIMARY KEY (project_id, depends_on_id),
+        CHECK (project_id != depends_on_id)
+    );
+    CREATE INDEX project_dependency_depends_on ON project_dependency (depends_on_id);",
+    // 14: the board; column order and WIP limits per workspace, card order per user
+    "CREATE TABLE board_column (
+        team_id INTEGER NOT NULL REFERENCES team (id) ON DELETE CASCADE,
+        status TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        wip_limit INTEGER,
+        PRIMARY KEY (team_id, status)
+    );
+    CREATE TABLE board_card (
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        project_id INTEGER NOT NULL REFERENCES project (id_proj) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (user_id, project_id)
+    );",
+    // 15: the secret behind each user's calendar feed URL
+    "CREATE TABLE calendar_feed (
+        user_id INTEGER PRIMARY KEY REFERENCES user (id) ON DELETE CASCADE,
+        token TEXT NOT NULL UNIQUE,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );",
+    // 16: full-text indexes over the tables' own text, kept up to date by triggers
+    "CREATE VIRTUAL TABLE project_search USING fts5(
+        name, description, content = 'project', content_rowid = 'id_proj',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER project_search_insert AFTER INSERT ON project BEGIN
+        INSERT INTO project_search (rowid, name, description)
+            VALUES (new.id_proj, new.name, new.description);
+    END;
+    CREATE TRIGGER project_search_delete AFTER DELETE ON project BEGIN
+        INSERT INTO project_search (project_search, rowid, name, description)
+            VALUES ('delete', old.id_proj, old.name, old.description);
+    END;
+    CREATE TRIGGER project_search_update AFTER UPDATE OF name, description ON project BEGIN
+        INSERT INTO project_search (project_search, rowid, name, description)
+            VALUES ('delete', old.id_proj, old.name, old.description);
+        INSERT INTO project_search (rowid, name, description)
+            VALUES (new.id_proj, new.name, new.description);
+    END;
+    INSERT INTO project_search (project_search) VALUES ('rebuild');
+
+    CREATE VIRTUAL TABLE task_search USING fts5(
+        title, content = 'task', content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER task_search_insert AFTER INSERT ON task BEGIN
+        INSERT INTO task_search (rowid, title) VALUES (new.id, new.title);
+    END;
+    CREATE TRIGGER task_search_delete AFTER DELETE ON task BEGIN
+        INSERT INTO task_search (task_search, rowid, title) VALUES ('delete', old.id, old.title);
+    END;
+    CREATE TRIGGER task_search_update AFTER UPDATE OF title ON task BEGIN
+        INSERT INTO task_search (task_search, rowid, title) VALUES ('delete', old.id, old.title);
+        INSERT INTO task_search (rowid, title) VALUES (new.id, new.title);
+    END;
+    INSERT INTO task_search (task_search) VALUES ('rebuild');
+
+    CREATE VIRTUAL TABLE comment_search USING fts5(
+        body, content = 'comment', content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER comment_search_insert AFTER INSERT ON comment BEGIN
+        INSERT INTO comment_search (rowid, body) VALUES (new.id, new.body);
+    END;
+    CREATE TRIGGER comment_search_delete AFTER DELETE ON comment BEGIN
+        INSERT INTO comment_search (comment_search, rowid, body) VALUES ('delete', old.id, old.body);
+    END;
+    CREATE TRIGGER comment_search_update AFTER UPDATE OF body ON comment BEGIN
+        INSERT INTO comment_search (comment_search, rowid, body) VALUES ('delete', old.id, old.body);
+        INSERT INTO comment_search (rowid, body) VALUES (new.id, new.body);
+    END;
+    INSERT INTO comment_search (comment_search) VALUES ('rebuild');
+
+    CREATE VIRTUAL TABLE user_search USING fts5(
+        email, content = 'user', content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER user_search_insert AFTER INSERT ON user BEGIN
+        INSERT INTO user_search (rowid, email) VALUES (new.id, new.email);
+    END;
+    CREATE TRIGGER user_search_delete AFTER DELETE ON user BEGIN
+        INSERT INTO user_search (user_search, rowid, email) VALUES ('delete', old.id, old.email);
+    END;
+    CREATE TRIGGER user_search_update AFTER UPDATE OF email ON user BEGIN
+        INSERT INTO user_search (user_search, rowid, email) VALUES ('delete', old.id, old.email);
+        INSERT INTO user_search (rowid, email) VALUES (new.id, new.email);
+    END;
+    INSERT INTO user_search (user_search) VALUES ('rebuild');",
+    // 17: named project filters per user and workspace, and the dashboard widgets built on them
+    "CREATE TABLE saved_filter (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        team_id INTEGER NOT NULL REFERENCES team (id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        query TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE (user_id, team_id, name)
+    );
+    CREATE TABLE dashboard_widget (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL REFERENCES user (id) ON DELETE CASCADE,
+        filter_id INTEGER NOT NULL REFERENCES saved_filter (id) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX dashboard_widget_user ON dashboard_widget (user_id, position);",
+    // 18: invitations sent to one address, which only that address can use
+    "ALTER TABLE invitation ADD COLUMN email TEXT;",
+    // 19: when a project was added, which for imported ones can be long after they started;
+    // older projects only have their start to go by
+    "ALTER TABLE project ADD COLUMN created_at DATETIME;
+    UPDATE project SET created_at = start_date;",
+];
+
+pub fn run_migrations() -> Result<usize, Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let applied: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    let tx = conn.transaction()?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+    }
+    tx.commit()?;
+
+    Ok(MIGRATIONS.len().saturating_sub(applied))
+}
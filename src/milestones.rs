@@ -0,0 +1,102 @@
+use crate::serialise::{parse_date, serialise_data};
+use rusqlite::{params, Connection, Error, Row};
+use serde::{Deserialize, Serialize};
+
+// a dated checkpoint within a project; `overdue` is worked out when it's read
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Milestone {
+    pub id: i64,
+    pub project_id: u8,
+    pub title: String,
+    pub due_date: String,
+    pub completed_at: Option<String>,
+    pub overdue: bool,
+}
+
+const MILESTONE_COLUMNS: &str = "id, project_id, title, due_date, completed_at";
+
+fn milestone_from_row(row: &Row) -> Result<Milestone, Error> {
+    let due_date: String = row.get(3)?;
+    let completed_at: Option<String> = row.get(4)?;
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    Ok(Milestone {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        title: row.get(2)?,
+        overdue: completed_at.is_none() && due_date < now,
+        due_date,
+        completed_at,
+    })
+}
+
+// soonest first
+pub fn query_milestones_for_project(project_id: u8) -> Result<Vec<Milestone>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM milestone WHERE project_id = ?1 ORDER BY due_date, id",
+        MILESTONE_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![project_id], milestone_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// every milestone of the team's projects, for the timeline
+pub fn query_milestones_for_team(team_id: i64) -> Result<Vec<Milestone>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM milestone
+            WHERE project_id IN (SELECT id_proj FROM project WHERE team_id = ?1)
+            ORDER BY due_date, id",
+        MILESTONE_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![team_id], milestone_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// the due date as stored, from the datepicker's value
+pub fn milestone_due_date(due_date: &str) -> Result<String, &'static str> {
+    parse_date(due_date).map_err(|_| "A milestone needs a due date")
+}
+
+// `due_date` comes from `milestone_due_date`
+pub fn add_milestone(project_id: u8, title: &str, due_date: &str) -> Result<i64, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO milestone (project_id, title, due_date) VALUES (?1, ?2, ?3)",
+        params![project_id, title, due_date],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+// marks the milestone reached, or open again
+pub fn set_milestone_completed(
+    project_id: u8,
+    milestone_id: i64,
+    completed: bool,
+) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let updated = conn.execute(
+        "UPDATE milestone
+            SET completed_at = CASE WHEN ?1 THEN datetime('now', 'localtime') END
+            WHERE project_id = ?2 AND id = ?3",
+        params![completed, project_id, milestone_id],
+    )?;
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn delete_milestone(project_id: u8, milestone_id: i64) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "DELETE FROM milestone WHERE project_id = ?1 AND id = ?2",
+        params![project_id, milestone_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
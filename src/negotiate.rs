@@ -0,0 +1,51 @@
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::{json, to_value, Json, Value};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+// renders `template` with `context`, or returns the same context as JSON when
+// the client prefers `application/json`
+pub struct Negotiated {
+    template: &'static str,
+    context: Value,
+    status: Status,
+}
+
+impl Negotiated {
+    pub fn new<C: Serialize>(template: &'static str, context: C) -> Self {
+        Negotiated {
+            template,
+            context: to_value(context).unwrap_or_default(),
+            status: Status::Ok,
+        }
+    }
+
+    // the error page, or `{"msg": …}`, with `status` either way
+    pub fn error(status: Status, msg: &str) -> Self {
+        Negotiated {
+            template: "error",
+            context: json!({ "msg": msg }),
+            status,
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for Negotiated {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let wants_json = request
+            .accept()
+            .map(|accept| accept.preferred().media_type().is_json())
+            .unwrap_or(false);
+
+        let mut response = if wants_json {
+            Json(self.context).respond_to(request)?
+        } else {
+            Template::render(self.template, self.context).respond_to(request)?
+        };
+        response.set_status(self.status);
+        response.set_raw_header("Vary", "Accept");
+        Ok(response)
+    }
+}
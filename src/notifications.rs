@@ -0,0 +1,152 @@
+use crate::serialise::serialise_data;
+use rusqlite::{params, Connection, Error, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Notification {
+    pub id: i64,
+    pub user_id: u8,
+    pub message: String,
+    pub link: String,
+    pub created_at: String,
+    pub read_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationPreference {
+    pub user_id: u8,
+    pub deadline_in_app: bool,
+    pub deadline_email: bool,
+}
+
+impl NotificationPreference {
+    // users that never saved their settings get in-app reminders only
+    pub fn default_for(user_id: u8) -> Self {
+        NotificationPreference {
+            user_id,
+            deadline_in_app: true,
+            deadline_email: false,
+        }
+    }
+}
+
+// for callers that are already inside a connection or transaction of their own
+pub fn add_notification(
+    conn: &Connection,
+    user_id: u8,
+    message: &str,
+    link: &str,
+) -> Result<i64, Error> {
+    conn.execute(
+        "INSERT INTO notification (user_id, message, link) VALUES (?1, ?2, ?3)",
+        params![user_id, message, link],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+// entry point for other modules: deadline checks, admin actions, sharing, ...
+pub fn enqueue_notification(user_id: u8, message: &str, link: &str) -> Result<i64, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    add_notification(&conn, user_id, message, link)
+}
+
+pub fn query_notifications_for_user(user_id: u8) -> Result<Vec<Notification>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT id, user_id, message, link, created_at, read_at FROM notification
+            WHERE user_id = ?1
+            ORDER BY id DESC",
+    )?;
+    let items_iter = statement.query_map(params![user_id], notification_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+pub fn query_notification_by_id(id: i64, user_id: u8) -> Result<Notification, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT id, user_id, message, link, created_at, read_at FROM notification
+            WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        notification_from_row,
+    )
+}
+
+pub fn count_unread_notifications(user_id: u8) -> Result<usize, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT COUNT(*) FROM notification WHERE user_id = ?1 AND read_at IS NULL",
+        params![user_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn mark_notification_read(id: i64, user_id: u8) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let updated = conn.execute(
+        "UPDATE notification SET read_at = COALESCE(read_at, CURRENT_TIMESTAMP)
+            WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+    )?;
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn mark_all_notifications_read(user_id: u8) -> Result<usize, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "UPDATE notification SET read_at = CURRENT_TIMESTAMP
+            WHERE user_id = ?1 AND read_at IS NULL",
+        params![user_id],
+    )
+}
+
+fn notification_from_row(row: &Row) -> Result<Notification, Error> {
+    Ok(Notification {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        message: row.get(2)?,
+        link: row.get(3)?,
+        created_at: row.get(4)?,
+        read_at: row.get(5)?,
+    })
+}
+
+pub fn query_notification_preference(user_id: u8) -> Result<NotificationPreference, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let preference = conn
+        .query_row(
+            "SELECT user_id, deadline_in_app, deadline_email
+                FROM notification_preference WHERE user_id = ?1",
+            params![user_id],
+            |row| {
+                Ok(NotificationPreference {
+                    user_id: row.get(0)?,
+                    deadline_in_app: row.get(1)?,
+                    deadline_email: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(preference.unwrap_or_else(|| NotificationPreference::default_for(user_id)))
+}
+
+pub fn save_notification_preference(preference: &NotificationPreference) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO notification_preference (user_id, deadline_in_app, deadline_email)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (user_id) DO UPDATE SET
+                deadline_in_app = excluded.deadline_in_app,
+                deadline_email = excluded.deadline_email",
+        params![
+            preference.user_id,
+            preference.deadline_in_app,
+            preference.deadline_email
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,403 @@
+use std::fmt::Write;
+
+// A4 in points, with the same margin all round
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 50.0;
+const CONTENT_WIDTH: f64 = PAGE_WIDTH - 2.0 * MARGIN;
+const BAR_COLOUR: &str = "0.263 0.627 0.278";
+
+// Helvetica's advance widths for ' ' to '~', in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+// how wide `text` comes out; bold is a little wider than the regular widths say
+fn text_width(text: &str, font: Font, size: f64) -> f64 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => u32::from(HELVETICA_WIDTHS[c as usize - 32]),
+            _ => 556,
+        })
+        .sum();
+    let scale = if font == Font::Bold { 1.07 } else { 1.0 };
+    f64::from(units) * size / 1000.0 * scale
+}
+
+// a PDF string in WinAnsiEncoding, the built-in fonts' encoding; anything it lacks becomes '?'
+fn pdf_string(text: &str) -> String {
+    let mut encoded = String::from("(");
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                encoded.push('\\');
+                encoded.push(c);
+                continue;
+            }
+            ' '..='~' => {
+                encoded.push(c);
+                continue;
+            }
+            '\u{a0}'..='\u{ff}' => c as u32,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' | '➖' => 0x97,
+            _ => u32::from(b'?'),
+        };
+        let _ = write!(encoded, "\\{:03o}", byte);
+    }
+    encoded.push(')');
+    encoded
+}
+
+// the longest start of `text` that fits `width`, with an ellipsis if anything was cut
+fn truncate(text: &str, font: Font, size: f64, width: f64) -> String {
+    if text_width(text, font, size) <= width {
+        return text.to_string();
+    }
+    let mut cut = String::new();
+    for c in text.chars() {
+        cut.push(c);
+        if text_width(&cut, font, size) + text_width("…", font, size) > width {
+            cut.pop();
+            break;
+        }
+    }
+    format!("{}…", cut.trim_end())
+}
+
+// `text` broken into lines no wider than `width`, at spaces where possible
+fn wrap(text: &str, font: Font, size: f64, width: f64) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if line.is_empty() || text_width(&candidate, font, size) <= width {
+                line = candidate;
+            } else {
+                let full = std::mem::replace(&mut line, word.to_string());
+                lines.push(truncate(&full, font, size, width));
+            }
+        }
+        // a single word wider than the page is cut rather than overflowing
+        lines.push(truncate(&line, font, size, width));
+    }
+    lines
+}
+
+// a document laid out top to bottom, starting a new page whenever the current one is full;
+// only the built-in Helvetica fonts are used, so nothing needs embedding
+pub struct Pdf {
+    title: String,
+    pages: Vec<String>,
+    content: String,
+    y: f64,
+}
+
+impl Pdf {
+    pub fn new(title: &str) -> Self {
+        Pdf {
+            title: title.to_string(),
+            pages: Vec::new(),
+            content: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    // moves down by `height`, turning the page first if that doesn't fit
+    fn advance(&mut self, height: f64) {
+        if self.y - height < MARGIN {
+            self.pages.push(std::mem::take(&mut self.content));
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    fn show(&mut self, x: f64, text: &str, font: Font, size: f64, grey: bool) {
+        let _ = writeln!(
+            self.content,
+            "BT {} g /{} {} Tf {:.2} {:.2} Td {} Tj ET",
+            if grey { "0.4" } else { "0" },
+            font.resource(),
+            size,
+            x,
+            self.y,
+            pdf_string(text)
+        );
+    }
+
+    fn lines(&mut self, text: &str, font: Font, size: f64, grey: bool) {
+        for line in wrap(text, font, size, CONTENT_WIDTH) {
+            self.advance(size * 1.35);
+            self.show(MARGIN, &line, font, size, grey);
+        }
+    }
+
+    pub fn title(&mut self, text: &str) {
+        self.lines(text, Font::Bold, 18.0, false);
+    }
+
+    pub fn heading(&mut self, text: &str) {
+        self.gap(8.0);
+        self.lines(text, Font::Bold, 12.0, false);
+        self.advance(3.0);
+        let _ = writeln!(
+            self.content,
+            "0.6 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S",
+            MARGIN,
+            self.y,
+            PAGE_WIDTH - MARGIN,
+            self.y
+        );
+        self.advance(2.0);
+    }
+
+    pub fn text(&mut self, text: &str) {
+        self.lines(text, Font::Regular, 10.0, false);
+    }
+
+    pub fn note(&mut self, text: &str) {
+        self.lines(text, Font::Regular, 8.0, true);
+    }
+
+    pub fn gap(&mut self, height: f64) {
+        self.advance(height);
+    }
+
+    // a horizontal bar filled to `percentage`, with `label` after it
+    pub fn bar(&mut self, percentage: f64, label: &str) {
+        let width = 200.0;
+        let height = 8.0;
+        self.advance(14.0);
+        let filled = width * percentage.clamp(0.0, 100.0) / 100.0;
+        let _ = writeln!(
+            self.content,
+            "0.85 g {x:.2} {y:.2} {width:.2} {height:.2} re f {BAR_COLOUR} rg {x:.2} {y:.2} {filled:.2} {height:.2} re f",
+            x = MARGIN,
+            y = self.y,
+        );
+        self.show(MARGIN + width + 8.0, label, Font::Regular, 10.0, false);
+    }
+
+    // columns get `widths` as shares of the page width; cells that don't fit are cut short
+    // and the header is repeated on every page the table runs onto
+    pub fn table(&mut self, header: &[&str], widths: &[f64], rows: &[Vec<String>]) {
+        let total: f64 = widths.iter().sum();
+        let columns: Vec<(f64, f64)> = widths
+            .iter()
+            .scan(MARGIN, |x, share| {
+                let width = CONTENT_WIDTH * share / total;
+                let column = (*x, width);
+                *x += width;
+                Some(column)
+            })
+            .collect();
+        let size = 9.0;
+        let row_height = size * 1.6;
+        let header_row = |pdf: &mut Pdf| {
+            pdf.advance(row_height);
+            for ((x, width), name) in columns.iter().zip(header) {
+                let name = truncate(name, Font::Bold, size, width - 4.0);
+                pdf.show(*x, &name, Font::Bold, size, false);
+            }
+        };
+        self.advance(4.0);
+        header_row(self);
+        for row in rows {
+            let page = self.pages.len();
+            self.advance(row_height);
+            if self.pages.len() != page {
+                self.y += row_height;
+                header_row(self);
+                self.advance(row_height);
+            }
+            for ((x, width), cell) in columns.iter().zip(row) {
+                if cell.is_empty() {
+                    continue;
+                }
+                let cell = truncate(cell, Font::Regular, size, width - 4.0);
+                self.show(*x, &cell, Font::Regular, size, false);
+            }
+        }
+    }
+
+    // the finished file; pages are numbered at the bottom
+    pub fn finish(mut self) -> Vec<u8> {
+        if !self.content.is_empty() || self.pages.is_empty() {
+            self.pages.push(std::mem::take(&mut self.content));
+        }
+        let page_count = self.pages.len();
+        // catalog, page tree, two fonts and the info dictionary come first, then a page
+        // object and its content stream for every page
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..page_count)
+                    .map(|page| format!("{} 0 R", 6 + 2 * page))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                page_count
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            format!(
+                "<< /Title {} /Producer (rocket-db-forms) >>",
+                pdf_string(&self.title)
+            ),
+        ];
+        for (index, content) in self.pages.iter().enumerate() {
+            let footer = format!("{} ➖ page {} of {}", self.title, index + 1, page_count);
+            let content = format!(
+                "{}BT 0.4 g /F1 8 Tf {:.2} {:.2} Td {} Tj ET\n",
+                content,
+                MARGIN,
+                MARGIN / 2.0,
+                pdf_string(&truncate(&footer, Font::Regular, 8.0, CONTENT_WIDTH))
+            );
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                7 + 2 * index
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+
+        // everything is ASCII, so string lengths are byte offsets
+        let mut file = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(file.len());
+            let _ = write!(file, "{} 0 obj\n{}\nendobj\n", index + 1, object);
+        }
+        let xref = file.len();
+        let _ = write!(file, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(file, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            file,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        file.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 'a' is 556 units wide, so 5.56 points at size 10, and so is '…'
+    const SIZE: f64 = 10.0;
+
+    #[test]
+    fn pdf_strings_escape_delimiters() {
+        assert_eq!(pdf_string("plain"), "(plain)");
+        assert_eq!(pdf_string(r"(a\b)"), r"(\(a\\b\))");
+    }
+
+    #[test]
+    fn pdf_strings_use_winansi_octal_escapes() {
+        assert_eq!(pdf_string("café"), r"(caf\351)");
+        assert_eq!(pdf_string("€5"), r"(\2005)");
+        assert_eq!(pdf_string("…"), r"(\205)");
+        assert_eq!(pdf_string("a ➖ b"), r"(a \227 b)");
+    }
+
+    #[test]
+    fn pdf_strings_replace_what_winansi_lacks() {
+        assert_eq!(pdf_string("✓ done"), r"(\077 done)");
+        assert_eq!(pdf_string("日本"), r"(\077\077)");
+    }
+
+    #[test]
+    fn truncate_leaves_text_that_fits() {
+        assert_eq!(truncate("aaa", Font::Regular, SIZE, 16.68), "aaa");
+    }
+
+    #[test]
+    fn truncate_cuts_with_an_ellipsis_inside_the_width() {
+        let cut = truncate("aaaaaaaaaa", Font::Regular, SIZE, 20.0);
+        assert_eq!(cut, "aa…");
+        assert!(text_width(&cut, Font::Regular, SIZE) <= 20.0);
+        // trailing spaces aren't left before the ellipsis
+        assert_eq!(truncate("aa aaaaaa", Font::Regular, SIZE, 22.0), "aa…");
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces() {
+        assert_eq!(
+            wrap("aa aa aa", Font::Regular, SIZE, 30.0),
+            vec!["aa aa", "aa"]
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_paragraphs_apart() {
+        assert_eq!(
+            wrap("aa\n\naa aa", Font::Regular, SIZE, 100.0),
+            vec!["aa", "", "aa aa"]
+        );
+    }
+
+    #[test]
+    fn wrap_cuts_words_wider_than_the_line() {
+        let long = "a".repeat(20);
+        assert_eq!(
+            wrap(&format!("{} aa", long), Font::Regular, SIZE, 20.0),
+            vec!["aa…", "aa"]
+        );
+        assert_eq!(
+            wrap(&format!("aa {}", long), Font::Regular, SIZE, 20.0),
+            vec!["aa", "aa…"]
+        );
+    }
+
+    #[test]
+    fn bold_takes_more_room() {
+        assert!(text_width("aa", Font::Bold, SIZE) > text_width("aa", Font::Regular, SIZE));
+        assert_eq!(wrap("aa aa", Font::Regular, SIZE, 26.0), vec!["aa aa"]);
+        assert_eq!(wrap("aa aa", Font::Bold, SIZE, 26.0), vec!["aa", "aa"]);
+    }
+}
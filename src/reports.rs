@@ -0,0 +1,320 @@
+use crate::db_queries::{
+    project_filter, project_from_row, query_all_users, query_project_summary, Project,
+    ProjectStatus, PROJECT_COLUMNS, PROJECT_SORTS,
+};
+use crate::dependencies::{query_dependencies, schedule_warnings};
+use crate::listing::ListQuery;
+use crate::members::{query_project_members, ProjectMember};
+use crate::milestones::{query_milestones_for_project, Milestone};
+use crate::pdf::Pdf;
+use crate::serialise::serialise_data;
+use crate::stats::{query_completion, Completion};
+use crate::tasks::{
+    query_task_progress, query_task_progress_by_project, query_tasks_for_project, Task,
+    TaskProgress, TaskStatus,
+};
+use crate::time_entries::query_project_time_totals;
+use rusqlite::{params_from_iter, Connection, Error};
+use serde::{Deserialize, Serialize};
+
+// open tasks listed before the rest are summed up, so the report stays on one page
+pub const REPORT_TASKS: usize = 15;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectReport {
+    pub project: Project,
+    pub owner: String,
+    pub members: Vec<ProjectMember>,
+    pub progress: TaskProgress,
+    // the first open tasks in board order
+    pub open_tasks: Vec<Task>,
+    pub more_tasks: usize,
+    pub milestones: Vec<Milestone>,
+    pub minutes: i64,
+    pub warnings: Vec<String>,
+    pub generated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusCount {
+    pub status: ProjectStatus,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SummaryRow {
+    pub project: Project,
+    pub owner: String,
+    pub progress: TaskProgress,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SummaryReport {
+    pub workspace: String,
+    pub total: usize,
+    pub status_counts: Vec<StatusCount>,
+    pub overdue: usize,
+    // for the whole workspace, whatever the filters
+    pub completion: Completion,
+    pub projects: Vec<SummaryRow>,
+    pub generated_at: String,
+}
+
+fn generated_at() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn duration(minutes: i64) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn or_unset(date: &str) -> &str {
+    if date.is_empty() {
+        "not set"
+    } else {
+        date
+    }
+}
+
+pub fn query_project_report(project: Project) -> Result<ProjectReport, Error> {
+    let project_id = project.id_proj.unwrap_or_default();
+    let members = query_project_members(project_id)?;
+    let owner = members
+        .iter()
+        .find(|member| member.user_id == project.user_id)
+        .map(|member| member.email.clone())
+        .unwrap_or_default();
+    let mut open_tasks: Vec<Task> = query_tasks_for_project(project_id)?
+        .into_iter()
+        .filter(|task| task.status != TaskStatus::Done)
+        .collect();
+    let more_tasks = open_tasks.len().saturating_sub(REPORT_TASKS);
+    open_tasks.truncate(REPORT_TASKS);
+    let minutes = query_project_time_totals(project_id)?
+        .iter()
+        .map(|entry| entry.minutes)
+        .sum();
+    let warnings = schedule_warnings(&project, &query_dependencies(project_id)?);
+
+    Ok(ProjectReport {
+        owner,
+        members,
+        progress: query_task_progress(project_id)?,
+        open_tasks,
+        more_tasks,
+        milestones: query_milestones_for_project(project_id)?,
+        minutes,
+        warnings,
+        generated_at: generated_at(),
+        project,
+    })
+}
+
+// the projects the list's filters pick out, all of them, in the list's order
+pub fn query_summary_report(
+    team_id: i64,
+    workspace: &str,
+    list: &ListQuery,
+    project_ids: Option<&[u8]>,
+) -> Result<SummaryReport, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let (filter, values) = project_filter(team_id, None, list, project_ids);
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM project WHERE {} {}",
+        PROJECT_COLUMNS,
+        filter,
+        list.order_by(PROJECT_SORTS, "id_proj")
+    ))?;
+    let items_iter = statement.query_map(params_from_iter(values), project_from_row)?;
+    let projects = serialise_data(items_iter);
+
+    let users = query_all_users(team_id)?;
+    let progress = query_task_progress_by_project(team_id)?;
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let (counts, overdue) = query_project_summary(team_id, list, project_ids, &now)?;
+
+    Ok(SummaryReport {
+        workspace: workspace.to_string(),
+        total: projects.len(),
+        status_counts: counts
+            .into_iter()
+            .map(|(status, count)| StatusCount { status, count })
+            .collect(),
+        overdue,
+        completion: query_completion(team_id)?,
+        projects: projects
+            .into_iter()
+            .map(|project| SummaryRow {
+                owner: users
+                    .iter()
+                    .find(|user| user.id == project.user_id)
+                    .map(|user| user.email.clone())
+                    .unwrap_or_default(),
+                progress: project
+                    .id_proj
+                    .and_then(|id| progress.get(&id.to_string()).cloned())
+                    .unwrap_or_default(),
+                project,
+            })
+            .collect(),
+        generated_at: generated_at(),
+    })
+}
+
+// the same sections as report-project.html.tera
+pub fn project_report_pdf(report: &ProjectReport) -> Vec<u8> {
+    let project = &report.project;
+    let mut pdf = Pdf::new(&format!("{} status report", project.name));
+    pdf.title(&project.name);
+    pdf.note(&format!("status report, {}", report.generated_at));
+    pdf.gap(6.0);
+    pdf.text(&format!(
+        "Status: {} ➖ Priority: {} ➖ Owner: {}",
+        project.status.as_str().replace('_', " "),
+        project.priority.as_str(),
+        report.owner
+    ));
+    pdf.text(&format!(
+        "Start: {} ➖ Due: {} ➖ End: {}",
+        or_unset(&project.start_date),
+        or_unset(&project.due_date),
+        or_unset(&project.end_date)
+    ));
+    if !project.description.is_empty() {
+        pdf.gap(4.0);
+        pdf.text(&project.description);
+    }
+
+    pdf.heading("Progress");
+    pdf.bar(
+        report.progress.percentage,
+        &format!(
+            "{} of {} tasks done ({:.0}%)",
+            report.progress.done, report.progress.total, report.progress.percentage
+        ),
+    );
+    pdf.text(&format!("Time logged: {}", duration(report.minutes)));
+    for warning in &report.warnings {
+        pdf.text(&format!("Warning: {}", warning));
+    }
+
+    if !report.milestones.is_empty() {
+        pdf.heading("Milestones");
+        let rows: Vec<Vec<String>> = report
+            .milestones
+            .iter()
+            .map(|milestone| {
+                vec![
+                    milestone.title.clone(),
+                    milestone.due_date.clone(),
+                    match (&milestone.completed_at, milestone.overdue) {
+                        (Some(completed_at), _) => format!("done {}", completed_at),
+                        (None, true) => "overdue".to_string(),
+                        (None, false) => "open".to_string(),
+                    },
+                ]
+            })
+            .collect();
+        pdf.table(&["milestone", "due", "state"], &[3.0, 1.5, 2.0], &rows);
+    }
+
+    pdf.heading("Open tasks");
+    if report.open_tasks.is_empty() {
+        pdf.text("No open tasks.");
+    } else {
+        let rows: Vec<Vec<String>> = report
+            .open_tasks
+            .iter()
+            .map(|task| {
+                vec![
+                    task.title.clone(),
+                    task.status.as_str().replace('_', " "),
+                    task.assignee_email.clone().unwrap_or_default(),
+                    task.due_date.clone(),
+                ]
+            })
+            .collect();
+        pdf.table(
+            &["task", "status", "assignee", "due"],
+            &[3.0, 1.0, 2.0, 1.5],
+            &rows,
+        );
+        if report.more_tasks > 0 {
+            pdf.note(&format!("and {} more", report.more_tasks));
+        }
+    }
+
+    pdf.heading("Members");
+    let members: Vec<String> = report
+        .members
+        .iter()
+        .map(|member| format!("{} ({})", member.email, member.role.as_str()))
+        .collect();
+    pdf.text(&members.join(", "));
+    pdf.finish()
+}
+
+// the same sections as report-summary.html.tera
+pub fn summary_report_pdf(report: &SummaryReport) -> Vec<u8> {
+    let mut pdf = Pdf::new(&format!("{} project summary", report.workspace));
+    pdf.title(&format!("Projects in {}", report.workspace));
+    pdf.note(&format!("summary report, {}", report.generated_at));
+    pdf.gap(6.0);
+    let mut counts: Vec<String> = report
+        .status_counts
+        .iter()
+        .map(|entry| {
+            format!(
+                "{} {}",
+                entry.count,
+                entry.status.as_str().replace('_', " ")
+            )
+        })
+        .collect();
+    if report.overdue > 0 {
+        counts.push(format!("{} overdue", report.overdue));
+    }
+    pdf.text(&format!("{} projects: {}", report.total, counts.join(", ")));
+
+    pdf.heading("Completion");
+    pdf.bar(
+        report.completion.rate,
+        &format!(
+            "{:.0}% of finished projects done ({} done, {} cancelled)",
+            report.completion.rate, report.completion.done, report.completion.cancelled
+        ),
+    );
+    if let Some(days) = report.completion.average_days {
+        pdf.text(&format!("Done projects took {:.1} days on average", days));
+    }
+
+    pdf.heading("Projects");
+    let rows: Vec<Vec<String>> = report
+        .projects
+        .iter()
+        .map(|row| {
+            vec![
+                row.project.name.clone(),
+                row.owner.clone(),
+                row.project.status.as_str().replace('_', " "),
+                row.project.priority.as_str().to_string(),
+                row.project
+                    .due_date
+                    .get(..10)
+                    .unwrap_or_default()
+                    .to_string(),
+                if row.progress.total > 0 {
+                    format!("{}/{}", row.progress.done, row.progress.total)
+                } else {
+                    String::new()
+                },
+            ]
+        })
+        .collect();
+    pdf.table(
+        &["project", "owner", "status", "priority", "due", "tasks"],
+        &[3.0, 2.5, 1.2, 1.0, 1.2, 0.8],
+        &rows,
+    );
+    pdf.finish()
+}
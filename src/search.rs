@@ -0,0 +1,138 @@
+use crate::serialise::serialise_data;
+use rusqlite::{params, Connection, Error};
+use serde::{Deserialize, Serialize};
+
+pub const MAX_RESULTS: usize = 50;
+
+// FTS5 marks matches with these control characters, swapped for <mark> after escaping
+const MATCH_START: &str = "\u{2}";
+const MATCH_END: &str = "\u{3}";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchResult {
+    // "project", "task", "comment" or "user"
+    pub kind: String,
+    pub id: i64,
+    pub title: String,
+    // HTML, escaped, with the matching words in <mark>
+    pub snippet: String,
+    pub url: String,
+    // bm25, lower is better
+    pub rank: f64,
+}
+
+// every word as a prefix search, all of them required; quoting keeps FTS5 syntax out of it
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn highlight(snippet: &str) -> String {
+    snippet
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+// the projects a user owns or was added to in the workspace
+const VISIBLE_PROJECTS: &str = "SELECT id_proj FROM project
+    WHERE team_id = ?2
+        AND (user_id = ?3 OR id_proj IN (SELECT project_id FROM project_member WHERE user_id = ?3))";
+
+// each query yields kind, id, title, snippet, url and rank
+fn search_queries() -> [String; 4] {
+    let snippet = |table: &str, column: i64| {
+        format!(
+            "snippet({}, {}, '{}', '{}', '…', 12)",
+            table, column, MATCH_START, MATCH_END
+        )
+    };
+    [
+        format!(
+            "SELECT 'project', project.id_proj, project.name, {},
+                    '/project/' || project.id_proj, project_search.rank
+                FROM project_search
+                JOIN project ON project.id_proj = project_search.rowid
+                WHERE project_search MATCH ?1 AND project.id_proj IN ({})
+                ORDER BY project_search.rank LIMIT ?4",
+            snippet("project_search", -1),
+            VISIBLE_PROJECTS
+        ),
+        format!(
+            "SELECT 'task', task.id, project.name, {},
+                    '/project/' || task.project_id || '/tasks', task_search.rank
+                FROM task_search
+                JOIN task ON task.id = task_search.rowid
+                JOIN project ON project.id_proj = task.project_id
+                WHERE task_search MATCH ?1 AND task.project_id IN ({})
+                ORDER BY task_search.rank LIMIT ?4",
+            snippet("task_search", 0),
+            VISIBLE_PROJECTS
+        ),
+        format!(
+            "SELECT 'comment', comment.id, project.name, {},
+                    '/project/' || comment.project_id || '#comment-' || comment.id,
+                    comment_search.rank
+                FROM comment_search
+                JOIN comment ON comment.id = comment_search.rowid
+                JOIN project ON project.id_proj = comment.project_id
+                WHERE comment_search MATCH ?1 AND comment.deleted_at IS NULL
+                    AND comment.project_id IN ({})
+                ORDER BY comment_search.rank LIMIT ?4",
+            snippet("comment_search", 0),
+            VISIBLE_PROJECTS
+        ),
+        // anyone in the same workspace
+        format!(
+            "SELECT 'user', user.id, user.email, {}, '/user/' || user.id, user_search.rank
+                FROM user_search
+                JOIN user ON user.id = user_search.rowid
+                JOIN team_member ON team_member.user_id = user.id AND team_member.team_id = ?2
+                WHERE user_search MATCH ?1
+                ORDER BY user_search.rank LIMIT ?4",
+            snippet("user_search", 0)
+        ),
+    ]
+}
+
+// the best matches for `query` across projects, tasks, comments and people, limited to
+// what `user_id` can open in the workspace
+pub fn search(
+    query: &str,
+    team_id: i64,
+    user_id: u8,
+    limit: usize,
+) -> Result<Vec<SearchResult>, Error> {
+    let Some(expression) = match_expression(query) else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.clamp(1, MAX_RESULTS);
+    let conn = Connection::open("db.sqlite")?;
+    let mut results = Vec::new();
+    for sql in search_queries() {
+        let mut statement = conn.prepare(&sql)?;
+        let items_iter =
+            statement.query_map(params![expression, team_id, user_id, limit], |row| {
+                Ok(SearchResult {
+                    kind: row.get(0)?,
+                    id: row.get(1)?,
+                    title: row.get(2)?,
+                    snippet: highlight(&row.get::<_, String>(3)?),
+                    url: row.get(4)?,
+                    rank: row.get(5)?,
+                })
+            })?;
+        results.extend(serialise_data(items_iter));
+    }
+    results.sort_by(|a, b| a.rank.total_cmp(&b.rank));
+    results.truncate(limit);
+    Ok(results)
+}
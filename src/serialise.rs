@@ -15,9 +15,11 @@ where
 // parses from "2020-01-01T00:00:00" to "2020-01-01 00:00:00"
 // "2020-01-01T00:00:00" is the format that the datepicker returns
 // "2020-01-01 00:00:00" is the format generated by 'DATETIME DEFAULT CURRENT_TIMESTAMP' in sqlite
+// browsers leave the seconds out of "2020-01-01T00:00" when they are zero
 pub fn parse_date(date: &str) -> Result<String, ()> {
     let parsed_end_date = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S")
-        .expect("Failed to parse date string");
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M"))
+        .map_err(|_| ())?;
     Ok(parsed_end_date.format("%Y-%m-%d %H:%M:%S").to_string())
 }
 
@@ -26,3 +28,28 @@ pub fn get_flash_msg(flash: Option<FlashMessage<'_>>) -> String {
         .map(|flash| format!("{}: {}", flash.kind(), flash.message()))
         .unwrap_or_default()
 }
+
+// stores a fieldless enum as its `as_str()` text, read back by matching against `ALL`
+macro_rules! sql_text_enum {
+    ($name:ident) => {
+        impl rusqlite::types::ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+                Ok(rusqlite::types::ToSqlOutput::from(self.as_str()))
+            }
+        }
+
+        impl rusqlite::types::FromSql for $name {
+            fn column_result(
+                value: rusqlite::types::ValueRef<'_>,
+            ) -> rusqlite::types::FromSqlResult<Self> {
+                let text = value.as_str()?;
+                $name::ALL
+                    .into_iter()
+                    .find(|candidate| candidate.as_str() == text)
+                    .ok_or(rusqlite::types::FromSqlError::InvalidType)
+            }
+        }
+    };
+}
+
+pub(crate) use sql_text_enum;
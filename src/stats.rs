@@ -0,0 +1,141 @@
+use crate::serialise::serialise_data;
+use crate::time_entries::week_start;
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection, Error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserProjects {
+    pub user_id: u8,
+    pub email: String,
+    pub total: usize,
+    pub open: usize,
+    pub done: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Completion {
+    pub total: usize,
+    pub done: usize,
+    pub cancelled: usize,
+    // done out of the projects that have finished one way or the other, 0 to 100
+    pub rate: f64,
+    // days from start to end of done projects
+    pub average_days: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeekActivity {
+    // the Monday starting the week
+    pub week: String,
+    pub created: usize,
+    pub completed: usize,
+}
+
+// owned projects per member of the workspace, busiest first; members without any are included
+pub fn query_projects_per_user(team_id: i64) -> Result<Vec<UserProjects>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT user.id, user.email, COUNT(project.id_proj),
+                COUNT(project.id_proj) FILTER (WHERE project.status NOT IN ('done', 'cancelled')),
+                COUNT(project.id_proj) FILTER (WHERE project.status = 'done')
+            FROM team_member
+            JOIN user ON user.id = team_member.user_id
+            LEFT JOIN project ON project.user_id = user.id AND project.team_id = team_member.team_id
+            WHERE team_member.team_id = ?1
+            GROUP BY user.id
+            ORDER BY COUNT(project.id_proj) DESC, user.email",
+    )?;
+    let items_iter = statement.query_map(params![team_id], |row| {
+        Ok(UserProjects {
+            user_id: row.get(0)?,
+            email: row.get(1)?,
+            total: row.get(2)?,
+            open: row.get(3)?,
+            done: row.get(4)?,
+        })
+    })?;
+
+    Ok(serialise_data(items_iter))
+}
+
+pub fn query_completion(team_id: i64) -> Result<Completion, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT COUNT(*),
+                COUNT(*) FILTER (WHERE status = 'done'),
+                COUNT(*) FILTER (WHERE status = 'cancelled'),
+                AVG(julianday(end_date) - julianday(start_date))
+                    FILTER (WHERE status = 'done' AND end_date != '' AND end_date >= start_date)
+            FROM project WHERE team_id = ?1",
+        params![team_id],
+        |row| {
+            let done: usize = row.get(1)?;
+            let cancelled: usize = row.get(2)?;
+            let finished = done + cancelled;
+            Ok(Completion {
+                total: row.get(0)?,
+                done,
+                cancelled,
+                rate: if finished > 0 {
+                    done as f64 / finished as f64 * 100.0
+                } else {
+                    0.0
+                },
+                average_days: row.get(3)?,
+            })
+        },
+    )
+}
+
+// `(Monday, count)` for every week something happened, `date_column` saying when
+fn weekly_counts(
+    conn: &Connection,
+    team_id: i64,
+    date_column: &str,
+    condition: &str,
+    since: NaiveDate,
+) -> Result<HashMap<String, usize>, Error> {
+    // SQLite's 'weekday 0' moves forward to Sunday, six days back from there is the Monday
+    let mut statement = conn.prepare(&format!(
+        "SELECT date({0}, '-1 day', 'weekday 0', '-6 days') AS week, COUNT(*)
+            FROM project
+            WHERE team_id = ?1 AND {0} != '' AND {0} >= ?2 AND {1}
+            GROUP BY week",
+        date_column, condition
+    ))?;
+    let items_iter = statement.query_map(
+        params![team_id, since.format("%Y-%m-%d").to_string()],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    Ok(serialise_data(items_iter).into_iter().collect())
+}
+
+// projects added and finished in each of the `weeks` weeks up to the one `today` is in,
+// oldest first, quiet weeks included
+pub fn query_weekly_activity(
+    team_id: i64,
+    weeks: i64,
+    today: NaiveDate,
+) -> Result<Vec<WeekActivity>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let this_week = week_start(today);
+    let first = this_week - Duration::weeks(weeks - 1);
+    let created = weekly_counts(&conn, team_id, "created_at", "1", first)?;
+    let completed = weekly_counts(&conn, team_id, "end_date", "status = 'done'", first)?;
+
+    Ok((0..weeks)
+        .map(|offset| {
+            let week = (first + Duration::weeks(offset))
+                .format("%Y-%m-%d")
+                .to_string();
+            WeekActivity {
+                created: created.get(&week).copied().unwrap_or_default(),
+                completed: completed.get(&week).copied().unwrap_or_default(),
+                week,
+            }
+        })
+        .collect())
+}
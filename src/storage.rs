@@ -0,0 +1,77 @@
+use crate::config::AppConfig;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug)]
+pub enum StorageError {
+    InvalidKey,
+    IoError(std::io::Error),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StorageError::InvalidKey => write!(f, "Invalid storage key"),
+            StorageError::IoError(e) => write!(f, "Storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+// where uploaded files live; keys are relative paths like `3/20240101120000-spec.pdf`
+pub trait Storage: Send + Sync {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), StorageError>;
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+    fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+// keeps files in a directory on the server's disk
+pub struct LocalStorage {
+    dir: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        LocalStorage { dir: dir.into() }
+    }
+
+    // keys never leave the storage directory
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let key = Path::new(key);
+        if key
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            Ok(self.dir.join(key))
+        } else {
+            Err(StorageError::InvalidKey)
+        }
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(StorageError::IoError)?;
+        }
+        fs::write(path, bytes).map_err(StorageError::IoError)
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        fs::read(self.path(key)?).map_err(StorageError::IoError)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.path(key)?) {
+            // already gone is as good as deleted
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result.map_err(StorageError::IoError),
+        }
+    }
+}
+
+pub fn storage_from_config(config: &AppConfig) -> Box<dyn Storage> {
+    Box::new(LocalStorage::new(&config.upload_dir))
+}
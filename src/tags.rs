@@ -0,0 +1,172 @@
+use crate::serialise::serialise_data;
+use rusqlite::{params, params_from_iter, Connection, Error, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// a workspace label; `text_colour` keeps the name readable on the chip
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tag {
+    pub id: i64,
+    pub team_id: i64,
+    pub name: String,
+    pub colour: String,
+    pub text_colour: String,
+    pub project_count: usize,
+}
+
+const TAG_COLUMNS: &str = "tag.id, tag.team_id, tag.name, tag.colour,
+    (SELECT COUNT(*) FROM project_tag WHERE project_tag.tag_id = tag.id)";
+
+fn tag_from_row(row: &Row) -> Result<Tag, Error> {
+    let colour: String = row.get(3)?;
+    Ok(Tag {
+        id: row.get(0)?,
+        team_id: row.get(1)?,
+        name: row.get(2)?,
+        text_colour: text_colour(&colour).to_string(),
+        colour,
+        project_count: row.get(4)?,
+    })
+}
+
+// `#rrggbb`, the format `<input type="color">` sends
+pub fn is_valid_colour(colour: &str) -> bool {
+    colour.len() == 7
+        && colour.starts_with('#')
+        && colour[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// black on light colours, white on dark ones
+fn text_colour(colour: &str) -> &'static str {
+    let channel = |range| u8::from_str_radix(colour.get(range).unwrap_or("00"), 16).unwrap_or(0);
+    let (r, g, b) = (
+        channel(1..3) as u32,
+        channel(3..5) as u32,
+        channel(5..7) as u32,
+    );
+    if r * 299 + g * 587 + b * 114 > 150_000 {
+        "#000000"
+    } else {
+        "#ffffff"
+    }
+}
+
+pub fn query_tags_for_team(team_id: i64) -> Result<Vec<Tag>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM tag WHERE team_id = ?1 ORDER BY tag.name",
+        TAG_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![team_id], tag_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+pub fn query_tags_for_project(project_id: u8) -> Result<Vec<Tag>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM tag
+            JOIN project_tag ON project_tag.tag_id = tag.id
+            WHERE project_tag.project_id = ?1
+            ORDER BY tag.name",
+        TAG_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![project_id], tag_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// keyed by project id as a string, like the task progress, so templates can index it
+pub fn query_tags_by_project(team_id: i64) -> Result<HashMap<String, Vec<Tag>>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {}, project_tag.project_id FROM tag
+            JOIN project_tag ON project_tag.tag_id = tag.id
+            WHERE tag.team_id = ?1
+            ORDER BY tag.name",
+        TAG_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![team_id], |row| {
+        let project_id: u8 = row.get(5)?;
+        Ok((project_id.to_string(), tag_from_row(row)?))
+    })?;
+
+    let mut tags: HashMap<String, Vec<Tag>> = HashMap::new();
+    for (project_id, tag) in serialise_data(items_iter) {
+        tags.entry(project_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+pub fn add_tag(team_id: i64, name: &str, colour: &str) -> Result<i64, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO tag (team_id, name, colour) VALUES (?1, ?2, ?3)",
+        params![team_id, name, colour],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn edit_tag(team_id: i64, tag_id: i64, name: &str, colour: &str) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let updated = conn.execute(
+        "UPDATE tag SET name = ?1, colour = ?2 WHERE team_id = ?3 AND id = ?4",
+        params![name, colour, team_id, tag_id],
+    )?;
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn delete_tag(team_id: i64, tag_id: i64) -> Result<(), Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    let deleted = tx.execute(
+        "DELETE FROM tag WHERE team_id = ?1 AND id = ?2",
+        params![team_id, tag_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    tx.execute("DELETE FROM project_tag WHERE tag_id = ?1", params![tag_id])?;
+    tx.commit()
+}
+
+// replaces the project's tags; ids from other workspaces are ignored
+pub fn set_project_tags(project_id: u8, team_id: i64, tag_ids: &[i64]) -> Result<(), Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM project_tag WHERE project_id = ?1",
+        params![project_id],
+    )?;
+    for tag_id in tag_ids {
+        tx.execute(
+            "INSERT OR IGNORE INTO project_tag (project_id, tag_id)
+                SELECT ?1, id FROM tag WHERE id = ?2 AND team_id = ?3",
+            params![project_id, tag_id, team_id],
+        )?;
+    }
+    tx.commit()
+}
+
+// ids of the workspace's projects carrying every one of `names`
+pub fn query_project_ids_with_tags(team_id: i64, names: &[String]) -> Result<Vec<u8>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let placeholders = vec!["?"; names.len()].join(", ");
+    let mut statement = conn.prepare(&format!(
+        "SELECT project_tag.project_id FROM project_tag
+            JOIN tag ON project_tag.tag_id = tag.id
+            WHERE tag.team_id = ? AND tag.name IN ({})
+            GROUP BY project_tag.project_id
+            HAVING COUNT(DISTINCT tag.id) = ?",
+        placeholders
+    ))?;
+    let mut values: Vec<rusqlite::types::Value> = vec![team_id.into()];
+    values.extend(names.iter().cloned().map(Into::into));
+    values.push((names.len() as i64).into());
+    let items_iter = statement.query_map(params_from_iter(values), |row| row.get(0))?;
+
+    Ok(serialise_data(items_iter))
+}
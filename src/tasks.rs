@@ -0,0 +1,272 @@
+use crate::db_queries::project_filter;
+use crate::listing::ListQuery;
+use crate::serialise::{parse_date, serialise_data, sql_text_enum};
+use rocket::form::FromFormField;
+use rusqlite::{params, params_from_iter, Connection, Error, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[field(value = "todo")]
+    Todo,
+    #[field(value = "in_progress")]
+    InProgress,
+    #[field(value = "done")]
+    Done,
+}
+
+impl TaskStatus {
+    pub const ALL: [TaskStatus; 3] = [TaskStatus::Todo, TaskStatus::InProgress, TaskStatus::Done];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Todo => "todo",
+            TaskStatus::InProgress => "in_progress",
+            TaskStatus::Done => "done",
+        }
+    }
+}
+
+sql_text_enum!(TaskStatus);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Task {
+    pub id: i64,
+    pub project_id: u8,
+    pub title: String,
+    pub status: TaskStatus,
+    pub assignee_id: Option<u8>,
+    pub assignee_email: Option<String>,
+    pub due_date: String,
+    pub position: i64,
+}
+
+#[derive(Debug)]
+pub struct TaskInput<'a> {
+    pub title: &'a str,
+    pub status: TaskStatus,
+    pub assignee_id: Option<u8>,
+    pub due_date: &'a str,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TaskProgress {
+    pub total: usize,
+    pub done: usize,
+    pub percentage: f64,
+}
+
+impl TaskProgress {
+    fn new(total: usize, done: usize) -> Self {
+        let percentage = if total > 0 {
+            (done as f64 / total as f64) * 100.0
+        } else {
+            0.0
+        };
+        TaskProgress {
+            total,
+            done,
+            percentage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum MoveDirection {
+    Up,
+    Down,
+}
+
+const TASK_COLUMNS: &str = "task.id, task.project_id, task.title, task.status, task.assignee_id,
+    user.email, task.due_date, task.position";
+
+fn task_from_row(row: &Row) -> Result<Task, Error> {
+    Ok(Task {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        title: row.get(2)?,
+        status: row.get(3)?,
+        assignee_id: row.get(4)?,
+        assignee_email: row.get(5)?,
+        due_date: row.get(6)?,
+        position: row.get(7)?,
+    })
+}
+
+fn parse_due_date(due_date: &str) -> Result<String, Error> {
+    if due_date.is_empty() {
+        Ok("".to_string())
+    } else {
+        parse_date(due_date).map_err(|_| rusqlite::Error::ExecuteReturnedResults)
+    }
+}
+
+pub fn query_tasks_for_project(project_id: u8) -> Result<Vec<Task>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM task
+            LEFT JOIN user ON task.assignee_id = user.id
+            WHERE task.project_id = ?1
+            ORDER BY task.position, task.id",
+        TASK_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![project_id], task_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+pub fn query_task_by_id(project_id: u8, task_id: i64) -> Result<Task, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM task
+                LEFT JOIN user ON task.assignee_id = user.id
+                WHERE task.project_id = ?1 AND task.id = ?2",
+            TASK_COLUMNS
+        ),
+        params![project_id, task_id],
+        task_from_row,
+    )
+}
+
+pub fn query_task_progress(project_id: u8) -> Result<TaskProgress, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(status = 'done'), 0) FROM task WHERE project_id = ?1",
+        params![project_id],
+        |row| Ok(TaskProgress::new(row.get(0)?, row.get(1)?)),
+    )
+}
+
+// the workspace's projects, keyed by project id as a string so templates can look projects up directly
+pub fn query_task_progress_by_project(
+    team_id: i64,
+) -> Result<HashMap<String, TaskProgress>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT task.project_id, COUNT(*), COALESCE(SUM(task.status = 'done'), 0) FROM task
+            JOIN project ON project.id_proj = task.project_id
+            WHERE project.team_id = ?1
+            GROUP BY task.project_id",
+    )?;
+    let items_iter = statement.query_map(params![team_id], |row| {
+        let project_id: u8 = row.get(0)?;
+        Ok((
+            project_id.to_string(),
+            TaskProgress::new(row.get(1)?, row.get(2)?),
+        ))
+    })?;
+
+    Ok(serialise_data(items_iter).into_iter().collect())
+}
+
+// every task of the projects the list's filters pick out, not just the ones on this page
+pub fn query_task_totals(
+    team_id: i64,
+    list: &ListQuery,
+    project_ids: Option<&[u8]>,
+) -> Result<TaskProgress, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let (filter, values) = project_filter(team_id, None, list, project_ids);
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(status = 'done'), 0) FROM task
+                WHERE project_id IN (SELECT id_proj FROM project WHERE {})",
+            filter
+        ),
+        params_from_iter(values),
+        |row| Ok(TaskProgress::new(row.get(0)?, row.get(1)?)),
+    )
+}
+
+pub fn add_task(project_id: u8, task: &TaskInput) -> Result<i64, Error> {
+    let due_date = parse_due_date(task.due_date)?;
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO task (project_id, title, status, assignee_id, due_date, position)
+            VALUES (?1, ?2, ?3, ?4, ?5,
+                (SELECT COALESCE(MAX(position), 0) + 1 FROM task WHERE project_id = ?1))",
+        params![
+            project_id,
+            task.title,
+            task.status,
+            task.assignee_id,
+            due_date
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn edit_task(project_id: u8, task_id: i64, task: &TaskInput) -> Result<(), Error> {
+    let due_date = parse_due_date(task.due_date)?;
+    let conn = Connection::open("db.sqlite")?;
+    let updated = conn.execute(
+        "UPDATE task SET title = ?1, status = ?2, assignee_id = ?3, due_date = ?4
+            WHERE project_id = ?5 AND id = ?6",
+        params![
+            task.title,
+            task.status,
+            task.assignee_id,
+            due_date,
+            project_id,
+            task_id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn delete_task(project_id: u8, task_id: i64) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "DELETE FROM task WHERE project_id = ?1 AND id = ?2",
+        params![project_id, task_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// swaps the task with its neighbour above or below; a no-op at either end
+pub fn move_task(project_id: u8, task_id: i64, direction: MoveDirection) -> Result<(), Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+
+    let position: i64 = tx.query_row(
+        "SELECT position FROM task WHERE project_id = ?1 AND id = ?2",
+        params![project_id, task_id],
+        |row| row.get(0),
+    )?;
+    let neighbour_query = match direction {
+        MoveDirection::Up => {
+            "SELECT id, position FROM task WHERE project_id = ?1 AND position < ?2
+                ORDER BY position DESC LIMIT 1"
+        }
+        MoveDirection::Down => {
+            "SELECT id, position FROM task WHERE project_id = ?1 AND position > ?2
+                ORDER BY position ASC LIMIT 1"
+        }
+    };
+    let neighbour: Option<(i64, i64)> = tx
+        .query_row(neighbour_query, params![project_id, position], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?;
+
+    if let Some((neighbour_id, neighbour_position)) = neighbour {
+        tx.execute(
+            "UPDATE task SET position = ?1 WHERE id = ?2",
+            params![neighbour_position, task_id],
+        )?;
+        tx.execute(
+            "UPDATE task SET position = ?1 WHERE id = ?2",
+            params![position, neighbour_id],
+        )?;
+    }
+    tx.commit()
+}
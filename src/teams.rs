@@ -0,0 +1,213 @@
+use crate::serialise::{serialise_data, sql_text_enum};
+use rocket::form::FromFormField;
+use rusqlite::{params, Connection, Error, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum TeamRole {
+    #[field(value = "admin")]
+    Admin,
+    #[field(value = "member")]
+    Member,
+}
+
+impl TeamRole {
+    pub const ALL: [TeamRole; 2] = [TeamRole::Admin, TeamRole::Member];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TeamRole::Admin => "admin",
+            TeamRole::Member => "member",
+        }
+    }
+}
+
+sql_text_enum!(TeamRole);
+
+// a team as seen by one of its members; the active one scopes every page
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+    pub role: TeamRole,
+}
+
+impl Workspace {
+    pub fn is_admin(&self) -> bool {
+        self.role == TeamRole::Admin
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TeamMember {
+    pub team_id: i64,
+    pub user_id: u8,
+    pub email: String,
+    pub role: TeamRole,
+}
+
+const WORKSPACE_COLUMNS: &str = "team.id, team.name, team_member.role";
+
+fn workspace_from_row(row: &Row) -> Result<Workspace, Error> {
+    Ok(Workspace {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        role: row.get(2)?,
+    })
+}
+
+pub fn query_workspaces_for_user(user_id: u8) -> Result<Vec<Workspace>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM team
+            JOIN team_member ON team_member.team_id = team.id
+            WHERE team_member.user_id = ?1
+            ORDER BY team.name, team.id",
+        WORKSPACE_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(params![user_id], workspace_from_row)?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// the requested team if the user belongs to it, otherwise the first one they joined
+pub fn query_active_workspace(user_id: u8, requested: Option<i64>) -> Result<Workspace, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM team
+                JOIN team_member ON team_member.team_id = team.id
+                WHERE team_member.user_id = ?1
+                ORDER BY team.id = ?2 DESC, team_member.added_at, team.id
+                LIMIT 1",
+            WORKSPACE_COLUMNS
+        ),
+        params![user_id, requested],
+        workspace_from_row,
+    )
+}
+
+pub fn is_team_member(team_id: i64, user_id: u8) -> Result<bool, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    Ok(team_role(&conn, team_id, user_id)?.is_some())
+}
+
+fn team_role(conn: &Connection, team_id: i64, user_id: u8) -> Result<Option<TeamRole>, Error> {
+    conn.query_row(
+        "SELECT role FROM team_member WHERE team_id = ?1 AND user_id = ?2",
+        params![team_id, user_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+pub fn insert_team_member(
+    conn: &Connection,
+    team_id: i64,
+    user_id: u8,
+    role: TeamRole,
+) -> Result<(), Error> {
+    conn.execute(
+        "INSERT INTO team_member (team_id, user_id, role) VALUES (?1, ?2, ?3)",
+        params![team_id, user_id, role],
+    )?;
+    Ok(())
+}
+
+// creates the team with `user_id` as its first admin
+pub fn add_team(conn: &Connection, name: &str, user_id: u8) -> Result<i64, Error> {
+    conn.execute("INSERT INTO team (name) VALUES (?1)", params![name])?;
+    let team_id = conn.last_insert_rowid();
+    insert_team_member(conn, team_id, user_id, TeamRole::Admin)?;
+    Ok(team_id)
+}
+
+pub fn create_team(name: &str, user_id: u8) -> Result<i64, Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    let team_id = add_team(&tx, name, user_id)?;
+    tx.commit()?;
+    Ok(team_id)
+}
+
+// admins first, then members
+pub fn query_team_members(team_id: i64) -> Result<Vec<TeamMember>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT team_member.team_id, team_member.user_id, user.email, team_member.role
+            FROM team_member
+            JOIN user ON team_member.user_id = user.id
+            WHERE team_member.team_id = ?1
+            ORDER BY team_member.role = 'admin' DESC, user.email",
+    )?;
+    let items_iter = statement.query_map(params![team_id], |row| {
+        Ok(TeamMember {
+            team_id: row.get(0)?,
+            user_id: row.get(1)?,
+            email: row.get(2)?,
+            role: row.get(3)?,
+        })
+    })?;
+
+    Ok(serialise_data(items_iter))
+}
+
+fn admin_count(conn: &Connection, team_id: i64) -> Result<usize, Error> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM team_member WHERE team_id = ?1 AND role = 'admin'",
+        params![team_id],
+        |row| row.get(0),
+    )
+}
+
+// a team always keeps at least one admin
+pub fn update_team_member_role(team_id: i64, user_id: u8, role: TeamRole) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let current = team_role(&conn, team_id, user_id)?.ok_or(Error::QueryReturnedNoRows)?;
+    if current == TeamRole::Admin && role != TeamRole::Admin && admin_count(&conn, team_id)? < 2 {
+        return Err(Error::InvalidQuery);
+    }
+    conn.execute(
+        "UPDATE team_member SET role = ?1 WHERE team_id = ?2 AND user_id = ?3",
+        params![role, team_id, user_id],
+    )?;
+    Ok(())
+}
+
+// members who still own projects in the team have to hand them over first;
+// their collaborator seats on the team's projects go with them
+pub fn remove_team_member(team_id: i64, user_id: u8) -> Result<(), Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+
+    let current = team_role(&tx, team_id, user_id)?.ok_or(Error::QueryReturnedNoRows)?;
+    if current == TeamRole::Admin && admin_count(&tx, team_id)? < 2 {
+        return Err(Error::InvalidQuery);
+    }
+    let owned_projects: usize = tx.query_row(
+        "SELECT COUNT(*) FROM project WHERE team_id = ?1 AND user_id = ?2",
+        params![team_id, user_id],
+        |row| row.get(0),
+    )?;
+    let other_teams: usize = tx.query_row(
+        "SELECT COUNT(*) FROM team_member WHERE user_id = ?1 AND team_id != ?2",
+        params![user_id, team_id],
+        |row| row.get(0),
+    )?;
+    // everyone keeps at least one workspace to land in
+    if owned_projects > 0 || other_teams == 0 {
+        return Err(Error::InvalidQuery);
+    }
+
+    tx.execute(
+        "DELETE FROM project_member WHERE user_id = ?1
+            AND project_id IN (SELECT id_proj FROM project WHERE team_id = ?2)",
+        params![user_id, team_id],
+    )?;
+    tx.execute(
+        "DELETE FROM team_member WHERE team_id = ?1 AND user_id = ?2",
+        params![team_id, user_id],
+    )?;
+    tx.commit()
+}
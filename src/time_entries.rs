@@ -0,0 +1,305 @@
+use crate::serialise::{parse_date, serialise_data};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime};
+use rocket_dyn_templates::tera::{self, Value};
+use rusqlite::{params, Connection, Error, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// times are local, like the ones typed into the forms
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeEntry {
+    pub id: i64,
+    pub project_id: u8,
+    pub project_name: String,
+    pub user_id: u8,
+    pub user_email: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub minutes: i64,
+    pub note: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserTime {
+    pub user_id: u8,
+    pub email: String,
+    pub minutes: i64,
+}
+
+// one project's minutes on each day of a week, Monday first
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimesheetRow {
+    pub project_id: u8,
+    pub project_name: String,
+    pub days: [i64; 7],
+    pub total: i64,
+}
+
+const TIME_ENTRY_COLUMNS: &str = "time_entry.id, time_entry.project_id, project.name,
+    time_entry.user_id, user.email, time_entry.started_at, time_entry.ended_at, time_entry.note";
+
+fn now() -> String {
+    Local::now().format(DATE_TIME_FORMAT).to_string()
+}
+
+// a running timer counts up to now
+fn minutes_between(started_at: &str, ended_at: Option<&str>) -> i64 {
+    let parse = |date: &str| NaiveDateTime::parse_from_str(date, DATE_TIME_FORMAT).ok();
+    let end = ended_at
+        .and_then(parse)
+        .unwrap_or_else(|| Local::now().naive_local());
+    parse(started_at)
+        .map(|start| (end - start).num_minutes().max(0))
+        .unwrap_or_default()
+}
+
+fn time_entry_from_row(row: &Row) -> Result<TimeEntry, Error> {
+    let started_at: String = row.get(5)?;
+    let ended_at: Option<String> = row.get(6)?;
+    Ok(TimeEntry {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        project_name: row.get(2)?,
+        user_id: row.get(3)?,
+        user_email: row.get(4)?,
+        minutes: minutes_between(&started_at, ended_at.as_deref()),
+        started_at,
+        ended_at,
+        note: row.get(7)?,
+    })
+}
+
+// start and end for a manual entry, from an end time or a duration in minutes
+pub fn entry_times(
+    started_at: &str,
+    ended_at: &str,
+    duration_minutes: Option<u32>,
+) -> Result<(String, String), &'static str> {
+    let started_at = parse_date(started_at).map_err(|_| "An entry needs a start time")?;
+    let ended_at = match (ended_at.is_empty(), duration_minutes) {
+        (false, _) => parse_date(ended_at).map_err(|_| "The end time isn't a valid time")?,
+        (true, Some(minutes)) if minutes > 0 => {
+            let start = NaiveDateTime::parse_from_str(&started_at, DATE_TIME_FORMAT)
+                .map_err(|_| "An entry needs a start time")?;
+            (start + Duration::minutes(minutes.into()))
+                .format(DATE_TIME_FORMAT)
+                .to_string()
+        }
+        _ => return Err("An entry needs an end time or a duration"),
+    };
+    if ended_at <= started_at {
+        return Err("An entry has to end after it starts");
+    }
+    Ok((started_at, ended_at))
+}
+
+pub fn add_time_entry(
+    project_id: u8,
+    user_id: u8,
+    started_at: &str,
+    ended_at: &str,
+    note: &str,
+) -> Result<i64, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.execute(
+        "INSERT INTO time_entry (project_id, user_id, started_at, ended_at, note)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![project_id, user_id, started_at, ended_at, note],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+// a user times one thing at a time, so starting a timer stops the one running
+pub fn start_timer(project_id: u8, user_id: u8, note: &str) -> Result<i64, Error> {
+    let mut conn = Connection::open("db.sqlite")?;
+    let tx = conn.transaction()?;
+    let now = now();
+    tx.execute(
+        "UPDATE time_entry SET ended_at = ?1 WHERE user_id = ?2 AND ended_at IS NULL",
+        params![now, user_id],
+    )?;
+    tx.execute(
+        "INSERT INTO time_entry (project_id, user_id, started_at, note) VALUES (?1, ?2, ?3, ?4)",
+        params![project_id, user_id, now, note],
+    )?;
+    let id = tx.last_insert_rowid();
+    tx.commit()?;
+    Ok(id)
+}
+
+pub fn stop_timer(user_id: u8) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let stopped = conn.execute(
+        "UPDATE time_entry SET ended_at = ?1 WHERE user_id = ?2 AND ended_at IS NULL",
+        params![now(), user_id],
+    )?;
+    if stopped == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn query_running_timer(user_id: u8) -> Result<Option<TimeEntry>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM time_entry
+                JOIN project ON time_entry.project_id = project.id_proj
+                JOIN user ON time_entry.user_id = user.id
+                WHERE time_entry.user_id = ?1 AND time_entry.ended_at IS NULL",
+            TIME_ENTRY_COLUMNS
+        ),
+        params![user_id],
+        time_entry_from_row,
+    )
+    .optional()
+}
+
+// only your own entries can be removed
+pub fn delete_time_entry(user_id: u8, entry_id: i64) -> Result<(), Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let deleted = conn.execute(
+        "DELETE FROM time_entry WHERE user_id = ?1 AND id = ?2",
+        params![user_id, entry_id],
+    )?;
+    if deleted == 0 {
+        return Err(Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+// finished entries per person, most time first
+pub fn query_project_time_totals(project_id: u8) -> Result<Vec<UserTime>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(
+        "SELECT time_entry.user_id, user.email,
+                CAST(ROUND(SUM(julianday(time_entry.ended_at) - julianday(time_entry.started_at))
+                    * 1440) AS INTEGER) AS minutes
+            FROM time_entry
+            JOIN user ON time_entry.user_id = user.id
+            WHERE time_entry.project_id = ?1 AND time_entry.ended_at IS NOT NULL
+            GROUP BY time_entry.user_id
+            ORDER BY minutes DESC, user.email",
+    )?;
+    let items_iter = statement.query_map(params![project_id], |row| {
+        Ok(UserTime {
+            user_id: row.get(0)?,
+            email: row.get(1)?,
+            minutes: row.get(2)?,
+        })
+    })?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// entries in the workspace starting on or after `from` and before `until`, oldest first;
+// `user_id` narrows them to one person
+pub fn query_time_entries_between(
+    team_id: i64,
+    user_id: Option<u8>,
+    from: NaiveDate,
+    until: NaiveDate,
+) -> Result<Vec<TimeEntry>, Error> {
+    let conn = Connection::open("db.sqlite")?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM time_entry
+            JOIN project ON time_entry.project_id = project.id_proj
+            JOIN user ON time_entry.user_id = user.id
+            WHERE project.team_id = ?1 AND (?2 IS NULL OR time_entry.user_id = ?2)
+                AND time_entry.started_at >= ?3 AND time_entry.started_at < ?4
+            ORDER BY time_entry.started_at, time_entry.id",
+        TIME_ENTRY_COLUMNS
+    ))?;
+    let items_iter = statement.query_map(
+        params![
+            team_id,
+            user_id,
+            from.format("%Y-%m-%d").to_string(),
+            until.format("%Y-%m-%d").to_string()
+        ],
+        time_entry_from_row,
+    )?;
+
+    Ok(serialise_data(items_iter))
+}
+
+// the Monday of the week `date` falls in
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday().into())
+}
+
+// totals per project and day for a week of entries
+pub fn timesheet_rows(entries: &[TimeEntry], monday: NaiveDate) -> Vec<TimesheetRow> {
+    let mut rows: Vec<TimesheetRow> = Vec::new();
+    let mut row_index: HashMap<u8, usize> = HashMap::new();
+    for entry in entries {
+        let Some(day) = entry
+            .started_at
+            .get(..10)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .map(|date| (date - monday).num_days())
+            .filter(|day| (0..7).contains(day))
+        else {
+            continue;
+        };
+        let index = *row_index.entry(entry.project_id).or_insert_with(|| {
+            rows.push(TimesheetRow {
+                project_id: entry.project_id,
+                project_name: entry.project_name.clone(),
+                days: [0; 7],
+                total: 0,
+            });
+            rows.len() - 1
+        });
+        rows[index].days[day as usize] += entry.minutes;
+        rows[index].total += entry.minutes;
+    }
+    rows.sort_by(|a, b| a.project_name.cmp(&b.project_name));
+    rows
+}
+
+// quotes fields that need it and defuses ones a spreadsheet would run as a formula
+pub fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+pub fn time_entries_csv(entries: &[TimeEntry]) -> String {
+    let mut csv = String::from("date,started_at,ended_at,minutes,hours,project,user,note\n");
+    for entry in entries {
+        let fields = [
+            entry.started_at.get(..10).unwrap_or_default().to_string(),
+            entry.started_at.clone(),
+            entry.ended_at.clone().unwrap_or_default(),
+            entry.minutes.to_string(),
+            format!("{:.2}", entry.minutes as f64 / 60.0),
+            entry.project_name.clone(),
+            entry.user_email.clone(),
+            entry.note.clone(),
+        ];
+        let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// `{{ minutes | duration }}` renders 95 as "1:35"
+pub fn duration_filter(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let minutes = tera::try_get_value!("duration", "value", i64, value);
+    Ok(Value::String(format!(
+        "{}:{:02}",
+        minutes / 60,
+        minutes % 60
+    )))
+}
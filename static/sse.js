@@ -0,0 +1,27 @@
+// Server-sent events for htmx: every element with sse-connect keeps an EventSource open to that
+// URL, and each event it receives is fired on the elements inside it whose hx-trigger names it,
+// e.g. hx-trigger="project-created, project-edited". Elements swapped in later are found too.
+document.addEventListener("DOMContentLoaded", () => {
+    const triggers = (element) =>
+        element
+            .getAttribute("hx-trigger")
+            .split(",")
+            .map((trigger) => trigger.trim().split(/\s+/)[0]);
+
+    for (const source of document.querySelectorAll("[sse-connect]")) {
+        const events = new EventSource(source.getAttribute("sse-connect"));
+        const names = new Set();
+        for (const element of source.querySelectorAll("[hx-trigger]")) {
+            triggers(element).forEach((name) => names.add(name));
+        }
+        for (const name of names) {
+            events.addEventListener(name, () => {
+                for (const element of source.querySelectorAll("[hx-trigger]")) {
+                    if (triggers(element).includes(name)) {
+                        htmx.trigger(element, name);
+                    }
+                }
+            });
+        }
+    }
+});